@@ -0,0 +1,15 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+fn bench_part1(c: &mut Criterion) {
+    let input = include_str!("../src/input.txt");
+
+    let mut group = c.benchmark_group("part1");
+    group.bench_function("checker", |b| b.iter(|| day01::part1(black_box(input))));
+    group.bench_function("bytes", |b| {
+        b.iter(|| day01::part1_bytes(black_box(input.as_bytes())))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_part1);
+criterion_main!(benches);
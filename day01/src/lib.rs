@@ -1,3 +1,5 @@
+use std::io::{self, BufRead};
+
 enum DigitChecker {
     Digit(char, u32),
     Word(&'static str, u32),
@@ -31,11 +33,12 @@ impl DigitChecker {
 }
 
 fn parse_line(line: &str, checker: &[DigitChecker]) -> u32 {
-    let x = checker
-        .iter()
-        .min_by_key(|c| c.leftmost_idx(line))
-        .unwrap()
-        .digit();
+    // a line without any digit counts as 0
+    let first = checker.iter().min_by_key(|c| c.leftmost_idx(line)).unwrap();
+    if first.leftmost_idx(line) == i32::MAX {
+        return 0;
+    }
+    let x = first.digit();
     let y = checker
         .iter()
         .max_by_key(|c| c.rightmost_index(line))
@@ -46,17 +49,7 @@ fn parse_line(line: &str, checker: &[DigitChecker]) -> u32 {
 }
 
 pub fn part1(input: &str) -> u32 {
-    let checker = vec![
-        DigitChecker::Digit('1', 1),
-        DigitChecker::Digit('2', 2),
-        DigitChecker::Digit('3', 3),
-        DigitChecker::Digit('4', 4),
-        DigitChecker::Digit('5', 5),
-        DigitChecker::Digit('6', 6),
-        DigitChecker::Digit('7', 7),
-        DigitChecker::Digit('8', 8),
-        DigitChecker::Digit('9', 9),
-    ];
+    let checker = part1_checker();
     input
         .trim()
         .lines()
@@ -64,6 +57,25 @@ pub fn part1(input: &str) -> u32 {
         .sum()
 }
 
+// byte-level part1: newlines located with memchr, digits scanned from both ends of each line
+#[cfg(feature = "fast")]
+pub fn part1_bytes(input: &[u8]) -> u32 {
+    let mut sum = 0;
+    let mut start = 0;
+    for end in memchr::memchr_iter(b'\n', input).chain(std::iter::once(input.len())) {
+        let line = &input[start..end];
+        start = end + 1;
+
+        // like the part1 checker, '0' is not a digit
+        let Some(first) = line.iter().find(|b| matches!(b, b'1'..=b'9')) else {
+            continue;
+        };
+        let last = line.iter().rfind(|b| matches!(b, b'1'..=b'9')).unwrap();
+        sum += ((first - b'0') * 10 + (last - b'0')) as u32;
+    }
+    sum
+}
+
 pub fn part2(input: &str) -> u32 {
     let checker = part2_checker();
     input
@@ -73,6 +85,86 @@ pub fn part2(input: &str) -> u32 {
         .sum()
 }
 
+pub fn part1_stream<R: BufRead>(reader: R) -> io::Result<u64> {
+    part1_running(reader).try_fold(0, |_, total| total)
+}
+
+pub fn part2_stream<R: BufRead>(reader: R) -> io::Result<u64> {
+    part2_running(reader).try_fold(0, |_, total| total)
+}
+
+pub fn part1_running<R: BufRead>(reader: R) -> RunningTotals<R> {
+    RunningTotals::new(reader, part1_checker())
+}
+
+pub fn part2_running<R: BufRead>(reader: R) -> RunningTotals<R> {
+    RunningTotals::new(reader, part2_checker())
+}
+
+// yields the calibration sum after every non-empty line, reading one line at a time
+pub struct RunningTotals<R> {
+    reader: R,
+    checker: Vec<DigitChecker>,
+    line: String,
+    total: u64,
+    done: bool,
+}
+
+impl<R: BufRead> RunningTotals<R> {
+    fn new(reader: R, checker: Vec<DigitChecker>) -> Self {
+        RunningTotals {
+            reader,
+            checker,
+            line: String::new(),
+            total: 0,
+            done: false,
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+}
+
+impl<R: BufRead> Iterator for RunningTotals<R> {
+    type Item = io::Result<u64>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => self.done = true,
+                Ok(_) => {
+                    let line = self.line.trim();
+                    if !line.is_empty() {
+                        self.total += parse_line(line, &self.checker) as u64;
+                        return Some(Ok(self.total));
+                    }
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+        None
+    }
+}
+
+fn part1_checker() -> Vec<DigitChecker> {
+    vec![
+        DigitChecker::Digit('1', 1),
+        DigitChecker::Digit('2', 2),
+        DigitChecker::Digit('3', 3),
+        DigitChecker::Digit('4', 4),
+        DigitChecker::Digit('5', 5),
+        DigitChecker::Digit('6', 6),
+        DigitChecker::Digit('7', 7),
+        DigitChecker::Digit('8', 8),
+        DigitChecker::Digit('9', 9),
+    ]
+}
+
 fn part2_checker() -> Vec<DigitChecker> {
     let checker = vec![
         DigitChecker::Digit('1', 1),
@@ -124,4 +216,33 @@ zoneight234
         let checker = part2_checker();
         assert_eq!(11, parse_line("one", &checker))
     }
+
+    #[cfg(feature = "fast")]
+    #[test]
+    fn test_bytes() {
+        let input = include_str!("./input.txt");
+        assert_eq!(part1(input), part1_bytes(input.as_bytes()));
+        assert_eq!(
+            142,
+            part1_bytes(b"1abc2\r\npqr3stu8vwx\na1b2c3d4e5f\ntreb7uchet\n")
+        );
+        assert_eq!(part1("a0b5"), part1_bytes(b"a0b5"));
+        assert_eq!(55, part1_bytes(b"a0b5"));
+        assert_eq!(0, part1("abc"));
+        assert_eq!(part1("abc"), part1_bytes(b"abc"));
+        let padded = "\n  1abc2\nxyz\r\n7\n\n";
+        assert_eq!(part1(padded), part1_bytes(padded.as_bytes()));
+    }
+
+    #[test]
+    fn test_stream() {
+        let input = include_str!("./input.txt");
+        assert_eq!(part1(input) as u64, part1_stream(input.as_bytes()).unwrap());
+        assert_eq!(part2(input) as u64, part2_stream(input.as_bytes()).unwrap());
+
+        let totals = part2_running("two1nine\n\neightwothree\r\nabcone2threexyz\n".as_bytes())
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(vec![29, 112, 125], totals);
+    }
 }
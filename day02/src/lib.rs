@@ -1,13 +1,69 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt,
+    io::{self, Write},
+};
+
 use nom::{
-    branch::alt,
     bytes::complete::tag,
-    character::complete::{line_ending, u32 as parse_u32},
-    combinator::{all_consuming, value},
+    character::complete::{alpha1, line_ending, u32 as parse_u32},
+    combinator::all_consuming,
     multi::separated_list0,
-    sequence::{delimited, terminated},
+    sequence::{delimited, separated_pair},
     IResult,
 };
 
+pub mod stats;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Color(usize);
+
+// colour names are interned in order of first appearance in the input
+#[derive(Debug, Default)]
+pub struct Palette {
+    names: Vec<String>,
+    ids: HashMap<String, Color>,
+}
+
+impl Palette {
+    pub fn intern(&mut self, name: &str) -> Color {
+        if let Some(color) = self.ids.get(name) {
+            return *color;
+        }
+        let color = Color(self.names.len());
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), color);
+        color
+    }
+
+    pub fn get(&self, name: &str) -> Option<Color> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn name(&self, color: Color) -> &str {
+        &self.names[color.0]
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn colors(&self) -> impl Iterator<Item = Color> {
+        (0..self.names.len()).map(Color)
+    }
+}
+
+#[derive(Debug)]
+pub struct Input {
+    palette: Palette,
+    games: Vec<Game>,
+}
+
 #[derive(Debug)]
 pub struct Game {
     id: u32,
@@ -15,88 +71,407 @@ pub struct Game {
 }
 
 #[derive(Debug)]
-struct Draw {
-    r: u32,
-    g: u32,
-    b: u32,
-}
-
-#[derive(Copy, Clone)]
-enum DrawColor {
-    Red,
-    Green,
-    Blue,
-}
-
-fn color(i: &str) -> IResult<&str, (u32, DrawColor)> {
-    let (i, num) = terminated(parse_u32, tag(" "))(i)?;
-    let (i, color) = alt((
-        value(DrawColor::Red, tag("red")),
-        value(DrawColor::Blue, tag("blue")),
-        value(DrawColor::Green, tag("green")),
-    ))(i)?;
-
-    Ok((i, (num, color)))
-}
-
-fn draw(i: &str) -> IResult<&str, Draw> {
-    let (i, colors) = separated_list0(tag(", "), color)(i)?;
-    let draw = colors
-        .iter()
-        .fold(Draw { r: 0, g: 0, b: 0 }, |mut draw, (num, color)| {
-            match color {
-                DrawColor::Red => draw.r = *num,
-                DrawColor::Green => draw.g = *num,
-                DrawColor::Blue => draw.b = *num,
+pub struct Draw {
+    cubes: Vec<(Color, u32)>,
+}
+
+// a draw that needs `excess` more cubes of `color` than the bag holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Violation {
+    pub game: u32,
+    pub draw: usize,
+    pub color: Color,
+    pub excess: u32,
+}
+
+// number of cubes per colour, indexed by `Color`; colours past the end count as 0
+#[derive(Debug, Clone, Default, Eq)]
+pub struct Bag {
+    counts: Vec<u32>,
+}
+
+impl PartialEq for Bag {
+    fn eq(&self, other: &Self) -> bool {
+        let len = self.counts.len().max(other.counts.len());
+        (0..len).all(|idx| self.get(Color(idx)) == other.get(Color(idx)))
+    }
+}
+
+impl Input {
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    pub fn games(&self) -> &[Game] {
+        &self.games
+    }
+
+    // builds a bag from colour names, ignoring colours that never appear in the input
+    pub fn bag(&self, contents: &[(&str, u32)]) -> Bag {
+        let mut bag = Bag::default();
+        for (name, num) in contents {
+            if let Some(color) = self.palette.get(name) {
+                bag.set(color, *num);
             }
-            draw
-        });
+        }
+        bag
+    }
+
+    pub fn possible_games<'a>(&'a self, bag: &'a Bag) -> impl Iterator<Item = &'a Game> + 'a {
+        self.games.iter().filter(|game| game.is_possible(bag))
+    }
+
+    pub fn violations(&self, bag: &Bag) -> Vec<Violation> {
+        self.games
+            .iter()
+            .flat_map(|game| game.violations(bag))
+            .collect()
+    }
+
+    pub fn minimal_bags(&self) -> impl Iterator<Item = (u32, Bag)> + '_ {
+        self.games.iter().map(|game| (game.id, game.minimal_bag()))
+    }
+}
+
+impl Game {
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn draws(&self) -> &[Draw] {
+        &self.draws
+    }
+
+    pub fn is_possible(&self, bag: &Bag) -> bool {
+        self.draws.iter().all(|draw| bag.contains(draw))
+    }
+
+    pub fn violations(&self, bag: &Bag) -> Vec<Violation> {
+        self.draws
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, draw)| {
+                bag.excess(draw).map(move |(color, excess)| Violation {
+                    game: self.id,
+                    draw: idx,
+                    color,
+                    excess,
+                })
+            })
+            .collect()
+    }
+
+    // the fewest cubes of each colour that make every draw possible
+    pub fn minimal_bag(&self) -> Bag {
+        self.draws.iter().fold(Bag::default(), |mut acc, draw| {
+            for (color, num) in &draw.cubes {
+                acc.set(*color, acc.get(*color).max(*num));
+            }
+            acc
+        })
+    }
+}
 
-    Ok((i, draw))
+impl Draw {
+    pub fn cubes(&self) -> &[(Color, u32)] {
+        &self.cubes
+    }
+
+    pub fn count(&self, color: Color) -> u32 {
+        self.cubes
+            .iter()
+            .filter(|(c, _)| *c == color)
+            .map(|(_, num)| num)
+            .sum()
+    }
 }
 
-fn game(i: &str) -> IResult<&str, Game> {
+impl Bag {
+    pub fn get(&self, color: Color) -> u32 {
+        self.counts.get(color.0).copied().unwrap_or(0)
+    }
+
+    pub fn set(&mut self, color: Color, num: u32) {
+        if self.counts.len() <= color.0 {
+            self.counts.resize(color.0 + 1, 0);
+        }
+        self.counts[color.0] = num;
+    }
+
+    pub fn contains(&self, draw: &Draw) -> bool {
+        self.excess(draw).next().is_none()
+    }
+
+    // colours the draw shows more of than the bag holds, with the difference
+    pub fn excess<'a>(&'a self, draw: &'a Draw) -> impl Iterator<Item = (Color, u32)> + 'a {
+        draw.cubes.iter().filter_map(|(color, num)| {
+            let available = self.get(*color);
+            (*num > available).then(|| (*color, num - available))
+        })
+    }
+
+    pub fn power(&self, palette: &Palette) -> u32 {
+        palette.colors().map(|color| self.get(color)).product()
+    }
+}
+
+pub struct GameDisplay<'a> {
+    game: &'a Game,
+    palette: &'a Palette,
+}
+
+impl Game {
+    pub fn display<'a>(&'a self, palette: &'a Palette) -> GameDisplay<'a> {
+        GameDisplay {
+            game: self,
+            palette,
+        }
+    }
+}
+
+impl fmt::Display for GameDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Game {}: ", self.game.id)?;
+        for (draw_idx, draw) in self.game.draws.iter().enumerate() {
+            if draw_idx > 0 {
+                f.write_str("; ")?;
+            }
+            for (cube_idx, (color, num)) in draw.cubes.iter().enumerate() {
+                if cube_idx > 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "{num} {}", self.palette.name(*color))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// one game per line, in the same format `parse` reads
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, game) in self.games.iter().enumerate() {
+            if idx > 0 {
+                f.write_str("\n")?;
+            }
+            write!(f, "{}", game.display(&self.palette))?;
+        }
+        Ok(())
+    }
+}
+
+// colour names are plain ascii letters, so neither format needs escaping
+impl Input {
+    pub fn write_json<W: Write>(&self, mut out: W) -> io::Result<()> {
+        write!(out, "[")?;
+        for (game_idx, game) in self.games.iter().enumerate() {
+            if game_idx > 0 {
+                write!(out, ",")?;
+            }
+            write!(out, "{{\"id\":{},\"draws\":[", game.id)?;
+            for (draw_idx, draw) in game.draws.iter().enumerate() {
+                if draw_idx > 0 {
+                    write!(out, ",")?;
+                }
+                write!(out, "[")?;
+                for (cube_idx, (color, num)) in draw.cubes.iter().enumerate() {
+                    if cube_idx > 0 {
+                        write!(out, ",")?;
+                    }
+                    write!(
+                        out,
+                        "{{\"color\":\"{}\",\"count\":{num}}}",
+                        self.palette.name(*color)
+                    )?;
+                }
+                write!(out, "]")?;
+            }
+            write!(out, "]}}")?;
+        }
+        writeln!(out, "]")
+    }
+
+    // one row per colour mention; `draw` is the 0-based index of the draw within its game
+    pub fn write_csv<W: Write>(&self, mut out: W) -> io::Result<()> {
+        writeln!(out, "game,draw,color,count")?;
+        for game in &self.games {
+            for (draw_idx, draw) in game.draws.iter().enumerate() {
+                for (color, num) in &draw.cubes {
+                    writeln!(
+                        out,
+                        "{},{draw_idx},{},{num}",
+                        game.id,
+                        self.palette.name(*color)
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    // "3 red, 4 red" is an error
+    #[default]
+    Reject,
+    // "3 red, 4 red" counts as 7 red, kept at the position of the first mention
+    Sum,
+}
+
+// line and column are 1-based and refer to the original input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    Syntax {
+        line: usize,
+        col: usize,
+    },
+    DuplicateColor {
+        line: usize,
+        col: usize,
+        color: String,
+    },
+    DuplicateGame {
+        line: usize,
+        col: usize,
+        id: u32,
+    },
+    CountOverflow {
+        line: usize,
+        col: usize,
+        color: String,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Syntax { line, col } => write!(f, "{line}:{col}: invalid syntax"),
+            ParseError::DuplicateColor { line, col, color } => {
+                write!(
+                    f,
+                    "{line}:{col}: colour {color:?} appears twice in one draw"
+                )
+            }
+            ParseError::DuplicateGame { line, col, id } => {
+                write!(f, "{line}:{col}: duplicate game id {id}")
+            }
+            ParseError::CountOverflow { line, col, color } => {
+                write!(f, "{line}:{col}: too many {color} cubes in one draw")
+            }
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+fn position(input: &str, at: &str) -> (usize, usize) {
+    let offset = at.as_ptr() as usize - input.as_ptr() as usize;
+    let before = &input[..offset];
+    let line = before.matches('\n').count() + 1;
+    let col = offset - before.rfind('\n').map(|i| i + 1).unwrap_or(0) + 1;
+    (line, col)
+}
+
+type RawDraw<'a> = Vec<(u32, &'a str)>;
+
+struct RawGame<'a> {
+    start: &'a str,
+    id: u32,
+    draws: Vec<RawDraw<'a>>,
+}
+
+fn color(i: &str) -> IResult<&str, (u32, &str)> {
+    separated_pair(parse_u32, tag(" "), alpha1)(i)
+}
+
+fn draw(i: &str) -> IResult<&str, RawDraw<'_>> {
+    separated_list0(tag(", "), color)(i)
+}
+
+fn game(i: &str) -> IResult<&str, RawGame<'_>> {
+    let start = i;
     let (i, id) = delimited(tag("Game "), parse_u32, tag(": "))(i)?;
     let (i, draws) = separated_list0(tag("; "), draw)(i)?;
-    Ok((i, Game { id, draws }))
+    Ok((i, RawGame { start, id, draws }))
 }
 
-pub fn parse(input: &str) -> Vec<Game> {
-    all_consuming(separated_list0(line_ending, game))(input.trim())
-        .unwrap()
-        .1
+pub fn parse(input: &str) -> Input {
+    try_parse(input, DuplicatePolicy::Reject).unwrap()
 }
 
-pub fn part1(input: &[Game]) -> u32 {
-    let (max_r, max_g, max_b) = (12u32, 13u32, 14u32);
+pub fn try_parse(input: &str, policy: DuplicatePolicy) -> Result<Input, ParseError> {
+    let raw = match all_consuming(separated_list0(line_ending, game))(input.trim()) {
+        Ok((_, raw)) => raw,
+        Err(nom::Err::Error(e) | nom::Err::Failure(e)) => {
+            let (line, col) = position(input, e.input);
+            return Err(ParseError::Syntax { line, col });
+        }
+        Err(nom::Err::Incomplete(_)) => unreachable!("complete parsers only"),
+    };
 
-    input
-        .iter()
-        .filter(|game| {
-            game.draws
-                .iter()
-                .all(|draw| draw.r <= max_r && draw.g <= max_g && draw.b <= max_b)
-        })
-        .map(|g| g.id)
-        .sum()
+    let mut palette = Palette::default();
+    let mut seen_ids = HashSet::new();
+    let mut games = Vec::with_capacity(raw.len());
+    for raw_game in raw {
+        if !seen_ids.insert(raw_game.id) {
+            let (line, col) = position(input, raw_game.start);
+            return Err(ParseError::DuplicateGame {
+                line,
+                col,
+                id: raw_game.id,
+            });
+        }
+
+        let mut draws = Vec::with_capacity(raw_game.draws.len());
+        for raw_draw in raw_game.draws {
+            let mut cubes: Vec<(Color, u32)> = Vec::with_capacity(raw_draw.len());
+            for (num, name) in raw_draw {
+                let color = palette.intern(name);
+                match cubes.iter_mut().find(|(c, _)| *c == color) {
+                    None => cubes.push((color, num)),
+                    Some((_, existing)) if policy == DuplicatePolicy::Sum => {
+                        *existing = existing.checked_add(num).ok_or_else(|| {
+                            let (line, col) = position(input, name);
+                            ParseError::CountOverflow {
+                                line,
+                                col,
+                                color: name.to_string(),
+                            }
+                        })?;
+                    }
+                    Some(_) => {
+                        let (line, col) = position(input, name);
+                        return Err(ParseError::DuplicateColor {
+                            line,
+                            col,
+                            color: name.to_string(),
+                        });
+                    }
+                }
+            }
+            draws.push(Draw { cubes });
+        }
+
+        games.push(Game {
+            id: raw_game.id,
+            draws,
+        });
+    }
+
+    Ok(Input { palette, games })
 }
 
-pub fn part2(input: Vec<Game>) -> u32 {
+pub fn part1(input: &Input) -> u32 {
+    let bag = input.bag(&[("red", 12), ("green", 13), ("blue", 14)]);
+
+    input.possible_games(&bag).map(|g| g.id).sum()
+}
+
+pub fn part2(input: Input) -> u32 {
     input
-        .into_iter()
-        .map(|game| {
-            let res = game
-                .draws
-                .iter()
-                .fold(Draw { r: 0, g: 0, b: 0 }, |mut acc, draw| {
-                    acc.r = acc.r.max(draw.r);
-                    acc.g = acc.g.max(draw.g);
-                    acc.b = acc.b.max(draw.b);
-                    acc
-                });
-
-            res.r * res.g * res.b
-        })
+        .minimal_bags()
+        .map(|(_, bag)| bag.power(&input.palette))
         .sum()
 }
 
@@ -104,6 +479,12 @@ pub fn part2(input: Vec<Game>) -> u32 {
 mod tests {
     use super::*;
 
+    const SAMPLE: &str = "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
+Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
+Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
+Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
+Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green";
+
     #[test]
     fn run02() {
         let input = include_str!("./input.txt");
@@ -111,4 +492,127 @@ mod tests {
         println!("{:?}", part1(&parsed));
         println!("{:?}", part2(parsed));
     }
+
+    #[test]
+    fn test_sample() {
+        let parsed = parse(SAMPLE);
+        assert_eq!(8, part1(&parsed));
+        assert_eq!(2286, part2(parsed));
+    }
+
+    #[test]
+    fn test_queries() {
+        let parsed = parse(SAMPLE);
+        let bag = parsed.bag(&[("red", 12), ("green", 13), ("blue", 14)]);
+        let red = parsed.palette().get("red").unwrap();
+
+        let possible = parsed
+            .possible_games(&bag)
+            .map(|g| g.id())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![1, 2, 5], possible);
+        assert_eq!(
+            vec![
+                Violation {
+                    game: 3,
+                    draw: 0,
+                    color: red,
+                    excess: 8
+                },
+                Violation {
+                    game: 4,
+                    draw: 2,
+                    color: parsed.palette().get("blue").unwrap(),
+                    excess: 1
+                },
+                Violation {
+                    game: 4,
+                    draw: 2,
+                    color: red,
+                    excess: 2
+                },
+            ],
+            parsed.violations(&bag)
+        );
+
+        let minimal = parsed.games()[0].minimal_bag();
+        assert_eq!(4, minimal.get(red));
+        assert_eq!(48, minimal.power(parsed.palette()));
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let input = "Game 1: 1 blue\nGame 2: 3 red, 2 blue, 4 red; 1 green";
+        assert_eq!(
+            ParseError::DuplicateColor {
+                line: 2,
+                col: 26,
+                color: "red".to_string()
+            },
+            try_parse(input, DuplicatePolicy::Reject).unwrap_err()
+        );
+
+        let summed = try_parse(input, DuplicatePolicy::Sum).unwrap();
+        let red = summed.palette().get("red").unwrap();
+        assert_eq!(7, summed.games()[1].draws()[0].count(red));
+        assert_eq!(2, summed.games()[1].draws()[0].cubes().len());
+        assert_eq!(
+            ParseError::CountOverflow {
+                line: 1,
+                col: 27,
+                color: "red".to_string()
+            },
+            try_parse("Game 1: 4294967295 red, 1 red", DuplicatePolicy::Sum).unwrap_err()
+        );
+
+        assert_eq!(
+            ParseError::DuplicateGame {
+                line: 3,
+                col: 1,
+                id: 1
+            },
+            try_parse(
+                "Game 1: 1 red\nGame 2: 1 red\nGame 1: 2 red",
+                DuplicatePolicy::Sum
+            )
+            .unwrap_err()
+        );
+        assert_eq!(
+            ParseError::Syntax { line: 1, col: 14 },
+            try_parse("Game 1: 1 red 2 blue", DuplicatePolicy::Sum).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let input = include_str!("./input.txt");
+        assert_eq!(input.trim(), parse(input).to_string());
+        assert_eq!(SAMPLE, parse(SAMPLE).to_string());
+
+        let parsed = parse("Game 3: 3 blue, 4 red; 2 green\nGame 7: 1 red");
+        let mut json = vec![];
+        parsed.write_json(&mut json).unwrap();
+        assert_eq!(
+            r#"[{"id":3,"draws":[[{"color":"blue","count":3},{"color":"red","count":4}],[{"color":"green","count":2}]]},{"id":7,"draws":[[{"color":"red","count":1}]]}]
+"#,
+            String::from_utf8(json).unwrap()
+        );
+
+        let mut csv = vec![];
+        parsed.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            "game,draw,color,count\n3,0,blue,3\n3,0,red,4\n3,1,green,2\n7,0,red,1\n",
+            String::from_utf8(csv).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_palette() {
+        let parsed = parse("Game 1: 2 yellow, 1 red; 3 yellow\nGame 2: 1 red, 4 violet, 2 yellow");
+        assert_eq!(3, parsed.palette().len());
+        assert_eq!(Some(Color(0)), parsed.palette().get("yellow"));
+        assert_eq!("violet", parsed.palette().name(Color(2)));
+        // game 1 never shows violet, so its power is 0
+        assert_eq!(8, part2(parsed));
+    }
 }
@@ -0,0 +1,192 @@
+use std::{f64::consts::TAU, sync::OnceLock};
+
+use crate::{Bag, Color, Draw, Game, Palette};
+
+// how the elf takes cubes out of the bag within a single draw; between draws all cubes
+// are put back, so draws are always independent of each other
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DrawModel {
+    WithReplacement,
+    WithoutReplacement,
+}
+
+const LN_FACTORIALS: usize = 1024;
+
+// exact up to LN_FACTORIALS, Stirling's series (error below 1e-17) above it
+fn ln_factorial(n: u64) -> f64 {
+    static TABLE: OnceLock<Vec<f64>> = OnceLock::new();
+    let table = TABLE.get_or_init(|| {
+        let mut table = vec![0.0; LN_FACTORIALS + 1];
+        for k in 2..=LN_FACTORIALS {
+            table[k] = table[k - 1] + (k as f64).ln();
+        }
+        table
+    });
+    if let Some(ln) = table.get(n as usize) {
+        return *ln;
+    }
+    let n = n as f64;
+    n * n.ln() - n + 0.5 * (TAU * n).ln() + 1.0 / (12.0 * n) - 1.0 / (360.0 * n * n * n)
+}
+
+fn ln_binomial(n: u64, k: u64) -> f64 {
+    ln_factorial(n) - ln_factorial(k) - ln_factorial(n - k)
+}
+
+fn total(bag: &Bag) -> u64 {
+    bag.counts.iter().map(|&num| u64::from(num)).sum()
+}
+
+// natural log of the probability of seeing exactly this draw; NEG_INFINITY if impossible
+pub fn draw_log_likelihood(bag: &Bag, draw: &Draw, model: DrawModel) -> f64 {
+    let bag_total = total(bag);
+    let draw_total = draw.cubes.iter().map(|&(_, num)| u64::from(num)).sum();
+    if draw_total > bag_total && (model == DrawModel::WithoutReplacement || bag_total == 0) {
+        return f64::NEG_INFINITY;
+    }
+
+    match model {
+        DrawModel::WithoutReplacement => {
+            let mut ll = -ln_binomial(bag_total, draw_total);
+            for (color, num) in &draw.cubes {
+                let available = bag.get(*color);
+                if *num > available {
+                    return f64::NEG_INFINITY;
+                }
+                ll += ln_binomial(available.into(), (*num).into());
+            }
+            ll
+        }
+        DrawModel::WithReplacement => {
+            let mut ll = ln_factorial(draw_total);
+            for (color, num) in &draw.cubes {
+                let available = bag.get(*color);
+                if *num == 0 {
+                    continue;
+                }
+                if available == 0 {
+                    return f64::NEG_INFINITY;
+                }
+                ll += *num as f64 * (available as f64 / bag_total as f64).ln()
+                    - ln_factorial((*num).into());
+            }
+            ll
+        }
+    }
+}
+
+pub fn game_log_likelihood(bag: &Bag, game: &Game, model: DrawModel) -> f64 {
+    game.draws
+        .iter()
+        .map(|draw| draw_log_likelihood(bag, draw, model))
+        .sum()
+}
+
+// candidates ordered from most to least likely to have produced the game
+pub fn rank_bags<'a>(game: &Game, candidates: &'a [Bag], model: DrawModel) -> Vec<(&'a Bag, f64)> {
+    let mut ranked = candidates
+        .iter()
+        .map(|bag| (bag, game_log_likelihood(bag, game, model)))
+        .collect::<Vec<_>>();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranked
+}
+
+// searches every bag that can explain the game and holds at most `max_total` cubes.
+// colours the game never shows only matter through the bag's total, so cubes beyond the
+// minimal bag go to the shown colours or to the first unshown one; with `k` such colours
+// that is C(spare + k, k) bags, e.g. about 4.6 million for 4 colours and 100 spare cubes
+pub fn most_likely_bag(
+    game: &Game,
+    palette: &Palette,
+    model: DrawModel,
+    max_total: u32,
+) -> Option<(Bag, f64)> {
+    let minimal = game.minimal_bag();
+    let needed = palette
+        .colors()
+        .map(|c| u64::from(minimal.get(c)))
+        .sum::<u64>();
+    let spare = u32::try_from(u64::from(max_total).checked_sub(needed)?).ok()?;
+
+    let shown = |color: &Color| {
+        game.draws
+            .iter()
+            .any(|draw| draw.cubes.iter().any(|(c, _)| c == color))
+    };
+    let mut colors = palette.colors().filter(shown).collect::<Vec<_>>();
+    colors.extend(palette.colors().find(|c| !shown(c)));
+
+    let mut best = None;
+    let mut bag = minimal.clone();
+    search(game, model, &colors, &minimal, spare, &mut bag, &mut best);
+    best
+}
+
+fn search(
+    game: &Game,
+    model: DrawModel,
+    colors: &[Color],
+    minimal: &Bag,
+    spare: u32,
+    bag: &mut Bag,
+    best: &mut Option<(Bag, f64)>,
+) {
+    let Some((color, rest)) = colors.split_first() else {
+        let ll = game_log_likelihood(bag, game, model);
+        if best.as_ref().is_none_or(|(_, best_ll)| ll > *best_ll) {
+            *best = Some((bag.clone(), ll));
+        }
+        return;
+    };
+
+    for extra in 0..=spare {
+        bag.set(*color, minimal.get(*color) + extra);
+        search(game, model, rest, minimal, spare - extra, bag, best);
+    }
+    bag.set(*color, minimal.get(*color));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_likelihood() {
+        let parsed = crate::parse("Game 1: 2 red; 1 red, 1 blue\nGame 2: 1 blue");
+        let game = &parsed.games()[0];
+        let even = parsed.bag(&[("red", 2), ("blue", 2)]);
+        let reddish = parsed.bag(&[("red", 3), ("blue", 1)]);
+
+        let ll = draw_log_likelihood(&even, &game.draws()[0], DrawModel::WithoutReplacement);
+        assert!((ll.exp() - 1.0 / 6.0).abs() < 1e-9);
+        let ll = draw_log_likelihood(&even, &game.draws()[0], DrawModel::WithReplacement);
+        assert!((ll.exp() - 0.25).abs() < 1e-9);
+
+        let candidates = [even.clone(), reddish.clone(), parsed.bag(&[("red", 5)])];
+        let ranked = rank_bags(game, &candidates, DrawModel::WithoutReplacement);
+        assert_eq!(&reddish, ranked[0].0);
+        assert_eq!(f64::NEG_INFINITY, ranked[2].1);
+
+        let (best, _) =
+            most_likely_bag(game, parsed.palette(), DrawModel::WithoutReplacement, 4).unwrap();
+        assert_eq!(reddish, best);
+        assert!(most_likely_bag(game, parsed.palette(), DrawModel::WithReplacement, 2).is_none());
+
+        let exact = (2..=2000u64).map(|k| (k as f64).ln()).sum::<f64>();
+        assert!((ln_factorial(2000) - exact).abs() < 1e-9);
+        assert!((ln_factorial(1025) - ln_factorial(1024) - 1025f64.ln()).abs() < 1e-9);
+
+        // the four colours game 1 never shows are searched as one
+        let parsed =
+            crate::parse("Game 1: 2 red; 1 red, 1 blue\nGame 2: 1 green, 1 cyan, 1 black, 1 white");
+        let game = &parsed.games()[0];
+        let (best, ll) =
+            most_likely_bag(game, parsed.palette(), DrawModel::WithoutReplacement, 100).unwrap();
+        assert!(total(&best) <= 100);
+        let reddish = parsed.bag(&[("red", 3), ("blue", 1)]);
+        assert!(ll >= game_log_likelihood(&reddish, game, DrawModel::WithoutReplacement));
+        let count = |name| u64::from(best.get(parsed.palette().get(name).unwrap()));
+        assert_eq!(total(&best), count("red") + count("blue") + count("green"));
+    }
+}
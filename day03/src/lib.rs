@@ -1,144 +1,542 @@
-use std::ops::RangeInclusive;
+use std::{error::Error, fmt, ops::RangeInclusive};
+
+pub mod render;
 
 pub struct Input {
+    grid: Vec<Vec<char>>,
     numbers: Vec<Vec<Number>>,
     symbols: Vec<Vec<Symbol>>,
 }
 
-struct Number {
-    cols: RangeInclusive<isize>, // including 1 col before and after
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NumberId {
+    pub row: usize,
+    pub idx: usize,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SymbolId {
+    pub row: usize,
+    pub idx: usize,
+}
+
+#[derive(Debug)]
+pub struct Number {
+    row: usize,
+    start: usize, // first digit
+    end: usize,   // last digit, inclusive
     value: u32,
+    raw: String,
+    symbols: Vec<SymbolId>,
 }
 
-struct Symbol {
-    col: isize,
+#[derive(Debug)]
+pub struct Symbol {
+    row: usize,
+    col: usize,
     symbol: char,
+    numbers: Vec<NumberId>,
 }
 
-pub fn parse(input: &str) -> Input {
-    let mut numbers = vec![];
-    let mut symbols = vec![];
-    for line in input.trim().lines() {
-        let mut line_numbers = vec![];
-        let mut line_symbols = vec![];
-
-        let mut num_acc = 0u32;
-        let mut len_acc = 0isize;
-
-        for (col, c) in line.chars().enumerate() {
-            if c.is_ascii_digit() {
-                let digit = c.to_digit(10).unwrap();
-                num_acc = num_acc * 10 + digit;
-                len_acc += 1;
-            } else {
-                if num_acc > 0 {
-                    line_numbers.push(Number {
-                        cols: (col as isize - (len_acc + 1))..=col as isize,
-                        value: num_acc,
-                    });
-
-                    num_acc = 0;
-                    len_acc = 0;
-                }
-                if c != '.' {
-                    line_symbols.push(Symbol {
-                        col: col as isize,
-                        symbol: c,
+impl Number {
+    pub fn row(&self) -> usize {
+        self.row
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn value(&self) -> u32 {
+        self.value
+    }
+
+    // the digits as written, including leading zeros
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    // whether something in `col` of this or a neighbouring row touches the number
+    pub fn touches(&self, col: usize) -> bool {
+        col + 1 >= self.start && col <= self.end + 1
+    }
+
+    pub fn symbols(&self) -> &[SymbolId] {
+        &self.symbols
+    }
+
+    pub fn is_part(&self) -> bool {
+        !self.symbols.is_empty()
+    }
+}
+
+impl Symbol {
+    pub fn row(&self) -> usize {
+        self.row
+    }
+
+    pub fn col(&self) -> usize {
+        self.col
+    }
+
+    pub fn symbol(&self) -> char {
+        self.symbol
+    }
+
+    pub fn numbers(&self) -> &[NumberId] {
+        &self.numbers
+    }
+}
+
+impl Input {
+    pub fn rows(&self) -> usize {
+        self.grid.len()
+    }
+
+    pub fn cell(&self, row: usize, col: usize) -> Option<char> {
+        self.grid.get(row)?.get(col).copied()
+    }
+
+    pub fn number(&self, id: NumberId) -> &Number {
+        &self.numbers[id.row][id.idx]
+    }
+
+    pub fn symbol(&self, id: SymbolId) -> &Symbol {
+        &self.symbols[id.row][id.idx]
+    }
+
+    pub fn numbers(&self) -> impl Iterator<Item = (NumberId, &Number)> {
+        self.numbers.iter().enumerate().flat_map(|(row, nums)| {
+            nums.iter()
+                .enumerate()
+                .map(move |(idx, num)| (NumberId { row, idx }, num))
+        })
+    }
+
+    pub fn symbols(&self) -> impl Iterator<Item = (SymbolId, &Symbol)> {
+        self.symbols.iter().enumerate().flat_map(|(row, syms)| {
+            syms.iter()
+                .enumerate()
+                .map(move |(idx, sym)| (SymbolId { row, idx }, sym))
+        })
+    }
+
+    pub fn numbers_touching(&self, symbol: char) -> impl Iterator<Item = &Number> {
+        self.numbers().map(|(_, num)| num).filter(move |num| {
+            num.symbols
+                .iter()
+                .any(|id| self.symbol(*id).symbol == symbol)
+        })
+    }
+
+    pub fn symbols_with_neighbours(&self, min: usize) -> impl Iterator<Item = &Symbol> {
+        self.symbols()
+            .map(|(_, sym)| sym)
+            .filter(move |sym| sym.numbers.len() >= min)
+    }
+
+    // rows next to `row` (including itself) that exist in the schematic
+    fn neighbour_rows(&self, row: usize) -> RangeInclusive<usize> {
+        row.saturating_sub(1)..=(row + 1).min(self.grid.len() - 1)
+    }
+
+    // recomputes the adjacency lists of all numbers and symbols in `rows`
+    fn link(&mut self, rows: RangeInclusive<usize>) {
+        for row in rows.clone() {
+            for idx in 0..self.numbers[row].len() {
+                let symbols = self
+                    .neighbour_rows(row)
+                    .flat_map(|other| {
+                        self.symbols[other]
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, sym)| self.numbers[row][idx].touches(sym.col))
+                            .map(move |(idx, _)| SymbolId { row: other, idx })
                     })
-                }
+                    .collect();
+                self.numbers[row][idx].symbols = symbols;
             }
         }
 
-        if num_acc > 0 {
-            let col = line.len();
-            line_numbers.push(Number {
-                cols: (col as isize - (len_acc + 1))..=col as isize,
-                value: num_acc,
-            });
+        for row in rows {
+            for idx in 0..self.symbols[row].len() {
+                let col = self.symbols[row][idx].col;
+                let numbers = self
+                    .neighbour_rows(row)
+                    .flat_map(|other| {
+                        self.numbers[other]
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, num)| num.touches(col))
+                            .map(move |(idx, _)| NumberId { row: other, idx })
+                    })
+                    .collect();
+                self.symbols[row][idx].numbers = numbers;
+            }
         }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NeighbourCount {
+    Exact(usize),
+    Min(usize),
+    Max(usize),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Reduction {
+    Product,
+    Sum,
+    Max,
+}
 
-        numbers.push(line_numbers);
-        symbols.push(line_symbols);
+// which symbols count as gears and how their adjacent numbers combine into a ratio
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GearRule {
+    pub symbols: Vec<char>,
+    pub neighbours: NeighbourCount,
+    pub reduction: Reduction,
+}
+
+impl Default for GearRule {
+    fn default() -> Self {
+        GearRule {
+            symbols: vec!['*'],
+            neighbours: NeighbourCount::Exact(2),
+            reduction: Reduction::Product,
+        }
+    }
+}
+
+impl GearRule {
+    pub fn matches(&self, symbol: &Symbol) -> bool {
+        let count = symbol.numbers.len();
+        self.symbols.contains(&symbol.symbol)
+            && match self.neighbours {
+                NeighbourCount::Exact(n) => count == n,
+                NeighbourCount::Min(n) => count >= n,
+                NeighbourCount::Max(n) => count <= n,
+            }
     }
 
-    Input { numbers, symbols }
+    // a symbol without neighbouring numbers has a ratio of 0 under every reduction
+    pub fn ratio(&self, input: &Input, symbol: &Symbol) -> Result<u64, Overflow> {
+        if symbol.numbers.is_empty() {
+            return Ok(0);
+        }
+        let mut values = symbol
+            .numbers
+            .iter()
+            .map(|id| u64::from(input.number(*id).value));
+        match self.reduction {
+            Reduction::Product => values.try_fold(1u64, |acc, value| acc.checked_mul(value)),
+            Reduction::Sum => values.try_fold(0u64, |acc, value| acc.checked_add(value)),
+            Reduction::Max => values.max(),
+        }
+        .ok_or(Overflow {
+            row: symbol.row,
+            col: symbol.col,
+        })
+    }
 }
 
-pub fn part1(input: &Input) -> u32 {
-    let mut sum = 0u32;
+// the ratio of the symbol at `row`, `col`, or the total once it is added, does not fit
+// in a u64
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Overflow {
+    pub row: usize,
+    pub col: usize,
+}
 
-    for (row_idx, row) in input.numbers.iter().enumerate() {
-        let empty = vec![];
-        let symbols_above = if row_idx > 0 {
-            &input.symbols[row_idx - 1]
-        } else {
-            &empty
-        };
-        let symbols = &input.symbols[row_idx];
-        let symbols_below = input.symbols.get(row_idx + 1).unwrap_or(&empty);
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: gear ratio overflows", self.row, self.col)
+    }
+}
 
-        for num in row {
-            if symbols_above.iter().any(|s| num.cols.contains(&s.col))
-                || symbols.iter().any(|s| num.cols.contains(&s.col))
-                || symbols_below.iter().any(|s| num.cols.contains(&s.col))
-            {
-                sum += num.value;
+impl Error for Overflow {}
+
+pub fn gears<'a>(input: &'a Input, rule: &'a GearRule) -> impl Iterator<Item = &'a Symbol> {
+    input
+        .symbols()
+        .map(|(_, sym)| sym)
+        .filter(|sym| rule.matches(sym))
+}
+
+pub fn evaluate(input: &Input, rule: &GearRule) -> Result<u64, Overflow> {
+    gears(input, rule).try_fold(0u64, |total, sym| {
+        total.checked_add(rule.ratio(input, sym)?).ok_or(Overflow {
+            row: sym.row,
+            col: sym.col,
+        })
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    NumberTooLarge { row: usize, col: usize, raw: String },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::NumberTooLarge { row, col, raw } => {
+                write!(f, "{row}:{col}: number {raw} is too large")
+            }
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditError {
+    RowOutOfRange { row: usize, rows: usize },
+    Parse(ParseError),
+    Overflow(Overflow),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::RowOutOfRange { row, rows } => {
+                write!(f, "row {row} is outside the schematic ({rows} rows)")
             }
+            EditError::Parse(err) => write!(f, "{err}"),
+            EditError::Overflow(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl Error for EditError {}
+
+impl From<ParseError> for EditError {
+    fn from(err: ParseError) -> Self {
+        EditError::Parse(err)
+    }
+}
+
+impl From<Overflow> for EditError {
+    fn from(err: Overflow) -> Self {
+        EditError::Overflow(err)
+    }
+}
+
+fn tokenize_row(row: usize, line: &str) -> Result<(Vec<Number>, Vec<Symbol>), ParseError> {
+    let mut numbers = vec![];
+    let mut symbols = vec![];
+    let mut raw = String::new();
+
+    for (col, c) in line.chars().chain(std::iter::once('.')).enumerate() {
+        if c.is_ascii_digit() {
+            raw.push(c);
+            continue;
+        }
+
+        if !raw.is_empty() {
+            let start = col - raw.len();
+            let value = raw.parse().map_err(|_| ParseError::NumberTooLarge {
+                row,
+                col: start,
+                raw: raw.clone(),
+            })?;
+            numbers.push(Number {
+                row,
+                start,
+                end: col - 1,
+                value,
+                raw: std::mem::take(&mut raw),
+                symbols: vec![],
+            });
+        }
+        if c != '.' {
+            symbols.push(Symbol {
+                row,
+                col,
+                symbol: c,
+                numbers: vec![],
+            })
         }
     }
 
-    sum
+    Ok((numbers, symbols))
 }
 
-pub fn part2(input: &Input) -> u32 {
-    let mut sum = 0u32;
+pub fn try_parse(input: &str) -> Result<Input, ParseError> {
+    let lines = input.trim().lines().collect::<Vec<_>>();
+    let grid = lines.iter().map(|line| line.chars().collect()).collect();
+    let (numbers, symbols) = lines
+        .iter()
+        .enumerate()
+        .map(|(row, line)| tokenize_row(row, line))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .unzip();
 
-    for (row_idx, row) in input.symbols.iter().enumerate() {
-        for symbol in row.iter().filter(|sym| sym.symbol == '*') {
-            let empty = vec![];
-            let numbers_above = if row_idx > 0 {
-                &input.numbers[row_idx - 1]
-            } else {
-                &empty
-            };
-            let numbers = &input.numbers[row_idx];
-            let numbers_below = input.numbers.get(row_idx + 1).unwrap_or(&empty);
+    let mut input = Input {
+        grid,
+        numbers,
+        symbols,
+    };
+    if !input.numbers.is_empty() {
+        input.link(0..=input.numbers.len() - 1);
+    }
+    Ok(input)
+}
+
+pub fn parse(input: &str) -> Input {
+    try_parse(input).unwrap()
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.grid {
+            writeln!(f, "{}", line.iter().collect::<String>())?;
+        }
+        Ok(())
+    }
+}
+
+type Row = (Vec<char>, Vec<Number>, Vec<Symbol>);
 
-            let matching_numbers = numbers_above
+// an editable schematic that keeps the part number and gear ratio sums up to date
+pub struct Schematic {
+    input: Input,
+    rule: GearRule,
+    part_sum: u64,
+    gear_sum: u64,
+}
+
+impl Schematic {
+    pub fn new(input: Input) -> Result<Self, Overflow> {
+        Self::with_rule(input, GearRule::default())
+    }
+
+    pub fn with_rule(input: Input, rule: GearRule) -> Result<Self, Overflow> {
+        let part_sum = part1(&input);
+        let gear_sum = evaluate(&input, &rule)?;
+        Ok(Schematic {
+            input,
+            rule,
+            part_sum,
+            gear_sum,
+        })
+    }
+
+    pub fn input(&self) -> &Input {
+        &self.input
+    }
+
+    pub fn part_sum(&self) -> u64 {
+        self.part_sum
+    }
+
+    pub fn gear_sum(&self) -> u64 {
+        self.gear_sum
+    }
+
+    // `row` must already exist, but rows are padded with '.' when writing past their end.
+    // only the edited row is re-tokenized, and only it and its neighbours are relinked.
+    // a rejected edit leaves the schematic unchanged
+    pub fn set(&mut self, row: usize, col: usize, c: char) -> Result<char, EditError> {
+        let rows = self.input.rows();
+        if row >= rows {
+            return Err(EditError::RowOutOfRange { row, rows });
+        }
+        let mut line = self.input.grid[row].clone();
+        if line.len() <= col {
+            line.resize(col + 1, '.');
+        }
+        let previous = std::mem::replace(&mut line[col], c);
+        let (numbers, symbols) = tokenize_row(row, &line.iter().collect::<String>())?;
+
+        // the rows' share of the totals fits, as the totals themselves do
+        let affected = self.input.neighbour_rows(row);
+        let (old_parts, old_gears) = self.sums(affected.clone())?;
+
+        let old_row = self.replace_row(row, (line, numbers, symbols));
+        let totals = self.sums(affected).and_then(|(parts, gears)| {
+            let overflow = Overflow { row, col };
+            Ok((
+                (self.part_sum - old_parts)
+                    .checked_add(parts)
+                    .ok_or(overflow)?,
+                (self.gear_sum - old_gears)
+                    .checked_add(gears)
+                    .ok_or(overflow)?,
+            ))
+        });
+        match totals {
+            Ok((part_sum, gear_sum)) => {
+                self.part_sum = part_sum;
+                self.gear_sum = gear_sum;
+                Ok(previous)
+            }
+            Err(err) => {
+                self.replace_row(row, old_row);
+                Err(err.into())
+            }
+        }
+    }
+
+    // swaps in the cells and tokens of `row`, relinking it and its neighbours
+    fn replace_row(&mut self, row: usize, (line, numbers, symbols): Row) -> Row {
+        let old = (
+            std::mem::replace(&mut self.input.grid[row], line),
+            std::mem::replace(&mut self.input.numbers[row], numbers),
+            std::mem::replace(&mut self.input.symbols[row], symbols),
+        );
+        self.input.link(self.input.neighbour_rows(row));
+        old
+    }
+
+    fn sums(&self, rows: RangeInclusive<usize>) -> Result<(u64, u64), Overflow> {
+        let mut parts = 0u64;
+        let mut gears = 0u64;
+        for row in rows {
+            parts += self.input.numbers[row]
+                .iter()
+                .filter(|num| num.is_part())
+                .map(|num| u64::from(num.value))
+                .sum::<u64>();
+            for sym in self.input.symbols[row]
                 .iter()
-                .filter(|num| num.cols.contains(&symbol.col))
-                .chain(numbers.iter().filter(|num| num.cols.contains(&symbol.col)))
-                .chain(
-                    numbers_below
-                        .iter()
-                        .filter(|num| num.cols.contains(&symbol.col)),
-                )
-                .collect::<Vec<_>>();
-
-            if matching_numbers.len() == 2 {
-                sum += matching_numbers[0].value * matching_numbers[1].value;
+                .filter(|sym| self.rule.matches(sym))
+            {
+                gears = gears
+                    .checked_add(self.rule.ratio(&self.input, sym)?)
+                    .ok_or(Overflow {
+                        row: sym.row,
+                        col: sym.col,
+                    })?;
             }
         }
+        Ok((parts, gears))
     }
+}
+
+pub fn part1(input: &Input) -> u64 {
+    input
+        .numbers()
+        .filter(|(_, num)| num.is_part())
+        .map(|(_, num)| u64::from(num.value))
+        .sum()
+}
 
-    sum
+pub fn part2(input: &Input) -> Result<u64, Overflow> {
+    evaluate(input, &GearRule::default())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn run03() {
-        let input = include_str!("./input.txt");
-        let parsed = parse(input);
-        println!("{:?}", part1(&parsed));
-        println!("{:?}", part2(&parsed));
-    }
-
-    #[test]
-    fn test_sample() {
-        let input = "467..114..
+    const SAMPLE: &str = "467..114..
 ...*......
 ..35..633.
 ......#...
@@ -148,8 +546,170 @@ mod tests {
 ......755.
 ...$.*....
 .664.598..";
+
+    #[test]
+    fn run03() {
+        let input = include_str!("./input.txt");
         let parsed = parse(input);
         println!("{:?}", part1(&parsed));
-        //println!("{:?}", part2(parsed));
+        println!("{:?}", part2(&parsed));
+    }
+
+    #[test]
+    fn test_sample() {
+        let parsed = parse(SAMPLE);
+        assert_eq!(4361, part1(&parsed));
+        assert_eq!(Ok(467835), part2(&parsed));
+    }
+
+    #[test]
+    fn test_adjacency() {
+        let parsed = parse(SAMPLE);
+
+        let (_, num) = parsed.numbers().next().unwrap();
+        assert_eq!(
+            (0, 0, 2, 467),
+            (num.row(), num.start(), num.end(), num.value())
+        );
+        let star = parsed.symbol(num.symbols()[0]);
+        assert_eq!(('*', 1, 3), (star.symbol(), star.row(), star.col()));
+        assert_eq!(2, star.numbers().len());
+
+        let hashed = parsed
+            .numbers_touching('#')
+            .map(|n| n.value())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![633], hashed);
+        assert_eq!(2, parsed.symbols_with_neighbours(2).count());
+    }
+
+    #[test]
+    fn test_zero_parts() {
+        let parsed = parse("0..007\n*..#..\n..000.");
+        let raw = parsed
+            .numbers()
+            .map(|(_, num)| (num.raw(), num.value(), num.start(), num.end()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![("0", 0, 0, 0), ("007", 7, 3, 5), ("000", 0, 2, 4)],
+            raw
+        );
+        assert!(parsed.numbers().all(|(_, num)| num.is_part()));
+        assert_eq!(7, part1(&parsed));
+        assert_eq!(4294967296, part1(&parse("4294967295*1")));
+    }
+
+    #[test]
+    fn test_schematic_edits() {
+        let mut schematic = Schematic::new(parse(SAMPLE)).unwrap();
+        assert_eq!((4361, 467835), (schematic.part_sum(), schematic.gear_sum()));
+
+        let edits = [
+            (0, 3, '*', '.'),
+            (3, 6, '.', '#'),
+            (2, 4, '7', '.'),
+            (4, 3, '1', '*'),
+            (9, 0, '#', '.'),
+            (9, 12, '3', '.'),
+            (8, 11, '+', '.'),
+            (1, 3, '.', '*'),
+        ];
+        for (row, col, c, expected_previous) in edits {
+            assert_eq!(expected_previous, schematic.set(row, col, c).unwrap());
+
+            let reparsed = parse(&schematic.input().to_string());
+            assert_eq!(part1(&reparsed), schematic.part_sum());
+            assert_eq!(part2(&reparsed), Ok(schematic.gear_sum()));
+        }
+        assert_eq!(
+            "#664.598....3",
+            schematic.input().to_string().lines().last().unwrap()
+        );
+
+        let mut schematic = Schematic::new(parse("429496729.*")).unwrap();
+        assert_eq!(
+            EditError::Parse(ParseError::NumberTooLarge {
+                row: 0,
+                col: 0,
+                raw: "4294967296".to_string()
+            }),
+            schematic.set(0, 9, '6').unwrap_err()
+        );
+        assert_eq!("429496729.*\n", schematic.input().to_string());
+        assert_eq!(0, schematic.part_sum());
+        assert_eq!('.', schematic.set(0, 9, '5').unwrap());
+        assert_eq!(4294967295, schematic.part_sum());
+
+        assert_eq!(
+            EditError::RowOutOfRange { row: 1, rows: 1 },
+            schematic.set(1, 0, '*').unwrap_err()
+        );
+        assert_eq!(
+            (4294967295, 0),
+            (schematic.part_sum(), schematic.gear_sum())
+        );
+
+        let mut schematic = Schematic::new(parse("4294967295*.")).unwrap();
+        assert_eq!('.', schematic.set(0, 11, '1').unwrap());
+        assert_eq!(4294967296, schematic.part_sum());
+
+        assert_eq!(
+            ParseError::NumberTooLarge {
+                row: 1,
+                col: 0,
+                raw: "99999999999".to_string()
+            },
+            try_parse("1\n99999999999*").err().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_gear_rules() {
+        let parsed = parse(SAMPLE);
+        let single = GearRule {
+            symbols: vec!['*', '#'],
+            neighbours: NeighbourCount::Max(1),
+            reduction: Reduction::Sum,
+        };
+        assert_eq!(Ok(617 + 633), evaluate(&parsed, &single));
+
+        let widest = GearRule {
+            symbols: vec!['*', '$', '+'],
+            neighbours: NeighbourCount::Min(1),
+            reduction: Reduction::Max,
+        };
+        assert_eq!(Ok(467 + 617 + 755 + 664 + 592), evaluate(&parsed, &widest));
+
+        let lonely = GearRule {
+            symbols: vec!['*'],
+            neighbours: NeighbourCount::Max(1),
+            reduction: Reduction::Product,
+        };
+        assert_eq!(Ok(0), evaluate(&parse("1....\n...*.\n....."), &lonely));
+        assert_eq!(Ok(617), evaluate(&parsed, &lonely));
+
+        assert_eq!(Ok(65536 * 65536), part2(&parse("65536*65536")));
+        let triple = GearRule {
+            symbols: vec!['*'],
+            neighbours: NeighbourCount::Min(3),
+            reduction: Reduction::Product,
+        };
+        let wide = "4294967295.4294967295\n..........*..........\n..........4294967295.";
+        assert_eq!(
+            Err(Overflow { row: 1, col: 10 }),
+            evaluate(&parse(wide), &triple)
+        );
+
+        let pair = wide.replace("..........4294967295.", ".....................");
+        let mut schematic = Schematic::with_rule(parse(&pair), triple).unwrap();
+        assert_eq!(
+            EditError::Overflow(Overflow { row: 1, col: 10 }),
+            schematic.set(2, 10, '9').unwrap_err()
+        );
+        assert_eq!(pair + "\n", schematic.input().to_string());
+        assert_eq!(
+            (2 * 4294967295, 0),
+            (schematic.part_sum(), schematic.gear_sum())
+        );
     }
 }
@@ -0,0 +1,135 @@
+use std::fmt::Write;
+
+use crate::{GearRule, Input};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    Ansi,
+    Html,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Class {
+    Empty,
+    Part,
+    NonPart,
+    Gear,
+    Symbol,
+}
+
+impl Class {
+    fn ansi(self) -> &'static str {
+        match self {
+            Class::Empty => "\x1b[2m",
+            Class::Part => "\x1b[32m",
+            Class::NonPart => "\x1b[31m",
+            Class::Gear => "\x1b[1;33m",
+            Class::Symbol => "\x1b[1m",
+        }
+    }
+
+    fn html(self) -> Option<&'static str> {
+        match self {
+            Class::Empty => None,
+            Class::Part => Some("part"),
+            Class::NonPart => Some("non-part"),
+            Class::Gear => Some("gear"),
+            Class::Symbol => Some("symbol"),
+        }
+    }
+}
+
+// part numbers, non-part numbers and symbols matching `rule` are marked in different colours
+pub fn render(input: &Input, rule: &GearRule, format: Format) -> String {
+    let mut classes = input
+        .grid
+        .iter()
+        .map(|line| vec![Class::Empty; line.len()])
+        .collect::<Vec<_>>();
+    for (_, num) in input.numbers() {
+        let class = if num.is_part() {
+            Class::Part
+        } else {
+            Class::NonPart
+        };
+        classes[num.row][num.start..=num.end].fill(class);
+    }
+    for (_, sym) in input.symbols() {
+        classes[sym.row][sym.col] = if rule.matches(sym) {
+            Class::Gear
+        } else {
+            Class::Symbol
+        };
+    }
+
+    let mut out = String::new();
+    if format == Format::Html {
+        out.push_str("<pre class=\"schematic\">\n");
+    }
+    for (line, line_classes) in input.grid.iter().zip(&classes) {
+        let mut current = None;
+        for (c, class) in line.iter().zip(line_classes) {
+            if current != Some(*class) {
+                close(&mut out, current, format);
+                open(&mut out, *class, format);
+                current = Some(*class);
+            }
+            match (format, c) {
+                (Format::Html, '<') => out.push_str("&lt;"),
+                (Format::Html, '>') => out.push_str("&gt;"),
+                (Format::Html, '&') => out.push_str("&amp;"),
+                _ => out.push(*c),
+            }
+        }
+        close(&mut out, current, format);
+        out.push('\n');
+    }
+    if format == Format::Html {
+        out.push_str("</pre>\n");
+    }
+    out
+}
+
+fn open(out: &mut String, class: Class, format: Format) {
+    match format {
+        Format::Ansi => out.push_str(class.ansi()),
+        Format::Html => {
+            if let Some(name) = class.html() {
+                write!(out, "<span class=\"{name}\">").unwrap();
+            }
+        }
+    }
+}
+
+fn close(out: &mut String, class: Option<Class>, format: Format) {
+    match (format, class) {
+        (Format::Ansi, Some(_)) => out.push_str("\x1b[0m"),
+        (Format::Html, Some(class)) if class.html().is_some() => out.push_str("</span>"),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_render() {
+        let parsed = parse("467..114\n...*....\n..35..&.");
+        assert_eq!(
+            "<pre class=\"schematic\">
+<span class=\"part\">467</span>..<span class=\"non-part\">114</span>
+...<span class=\"gear\">*</span>....
+..<span class=\"part\">35</span>..<span class=\"symbol\">&amp;</span>.
+</pre>
+",
+            render(&parsed, &GearRule::default(), Format::Html)
+        );
+
+        let ansi = render(&parsed, &GearRule::default(), Format::Ansi);
+        assert!(ansi.starts_with("\x1b[32m467\x1b[0m\x1b[2m..\x1b[0m\x1b[31m114\x1b[0m"));
+        assert_eq!(1, ansi.matches("\x1b[1;33m*").count());
+        assert_eq!(1, ansi.matches("\x1b[1m&").count());
+    }
+}
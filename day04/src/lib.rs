@@ -1,3 +1,11 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt,
+};
+
+use num::{CheckedAdd, One, Zero};
+
 use nom::{
     bytes::complete::tag,
     character::complete::{line_ending, space1, u32 as parse_u32},
@@ -9,70 +17,322 @@ use nom::{
 
 #[derive(Debug)]
 pub struct Card {
-    _id: u32,
+    id: u32,
     winning: Vec<u32>,
     yours: Vec<u32>,
+    matches: usize,
+}
+
+impl Card {
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn winning(&self) -> &[u32] {
+        &self.winning
+    }
+
+    pub fn yours(&self) -> &[u32] {
+        &self.yours
+    }
+
+    // how many of your numbers are winning numbers
+    pub fn matches(&self) -> usize {
+        self.matches
+    }
+}
+
+// card numbers are tiny in practice, so they usually fit a bitset
+enum NumberSet {
+    Bits(u128),
+    Hashed(HashSet<u32>),
+}
+
+impl NumberSet {
+    fn new(numbers: &[u32]) -> Self {
+        if numbers.iter().all(|n| *n < 128) {
+            NumberSet::Bits(numbers.iter().fold(0, |bits, n| bits | 1 << n))
+        } else {
+            NumberSet::Hashed(numbers.iter().copied().collect())
+        }
+    }
+
+    fn contains(&self, n: u32) -> bool {
+        match self {
+            NumberSet::Bits(bits) => n < 128 && bits & (1 << n) != 0,
+            NumberSet::Hashed(set) => set.contains(&n),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    Syntax,
+    DuplicateNumber { card: u32, number: u32 },
+    DuplicateCard { card: u32 },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Syntax => write!(f, "invalid syntax"),
+            ParseError::DuplicateNumber { card, number } => {
+                write!(f, "card {card} lists {number} twice")
+            }
+            ParseError::DuplicateCard { card } => write!(f, "card {card} appears twice"),
+        }
+    }
 }
 
+impl Error for ParseError {}
+
 fn number_list(i: &str) -> IResult<&str, Vec<u32>> {
     separated_list0(space1, parse_u32)(i.trim())
 }
 
-fn card(i: &str) -> IResult<&str, Card> {
+fn card(i: &str) -> IResult<&str, (u32, Vec<u32>, Vec<u32>)> {
     let (i, (_, _, id, _, _)) = tuple((tag("Card"), space1, parse_u32, tag(":"), space1))(i)?;
     let (i, (winning, yours)) = separated_pair(number_list, tag(" | "), number_list)(i)?;
 
-    Ok((
-        i,
-        Card {
-            _id: id,
-            winning,
-            yours,
-        },
-    ))
+    Ok((i, (id, winning, yours)))
+}
+
+fn check_unique(id: u32, numbers: &[u32]) -> Result<(), ParseError> {
+    let mut seen = HashSet::new();
+    match numbers.iter().find(|n| !seen.insert(**n)) {
+        Some(number) => Err(ParseError::DuplicateNumber {
+            card: id,
+            number: *number,
+        }),
+        None => Ok(()),
+    }
 }
 
 pub fn parse(input: &str) -> Vec<Card> {
-    all_consuming(separated_list0(line_ending, card))(input.trim())
-        .unwrap()
-        .1
+    try_parse(input).unwrap()
 }
 
-pub fn part1(input: &[Card]) -> u32 {
-    input
-        .iter()
-        .map(|card| {
-            card.yours
-                .iter()
-                .filter(|my_number| card.winning.contains(my_number))
-                .fold(0, |acc, _| if acc == 0 { 1 } else { acc * 2 })
+pub fn try_parse(input: &str) -> Result<Vec<Card>, ParseError> {
+    let raw = all_consuming(separated_list0(line_ending, card))(input.trim())
+        .map_err(|_| ParseError::Syntax)?
+        .1;
+
+    let mut cards = raw
+        .into_iter()
+        .map(|(id, winning, yours)| {
+            check_unique(id, &winning)?;
+            check_unique(id, &yours)?;
+
+            let set = NumberSet::new(&winning);
+            let matches = yours.iter().filter(|n| set.contains(**n)).count();
+            Ok(Card {
+                id,
+                winning,
+                yours,
+                matches,
+            })
         })
-        .sum()
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // copies are won by id, so shuffled tables are put back in order
+    cards.sort_by_key(|card| card.id);
+    if let Some(pair) = cards.windows(2).find(|pair| pair[0].id == pair[1].id) {
+        return Err(ParseError::DuplicateCard { card: pair[0].id });
+    }
+    Ok(cards)
 }
 
-pub fn part2(input: Vec<Card>) -> u32 {
-    let mut num_cards = vec![1u32; input.len()];
+// `None` if a card's points, or their sum, do not fit in a u64
+pub fn part1(input: &[Card]) -> Option<u64> {
+    input.iter().try_fold(0u64, |sum, card| {
+        let points = match card.matches {
+            0 => 0,
+            n => 1u64.checked_shl(u32::try_from(n - 1).ok()?)?,
+        };
+        sum.checked_add(points)
+    })
+}
 
-    for (idx, card) in input.into_iter().enumerate() {
-        let matches = card
-            .yours
-            .iter()
-            .filter(|my_number| card.winning.contains(my_number))
-            .count();
+// `amount` instances of card `to` were won by the instances of card `from`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Copies<C = u32> {
+    pub from: u32,
+    pub to: u32,
+    pub amount: C,
+}
+
+// card `card` wins `missing` more cards than are left in the table
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Overflow {
+    pub card: u32,
+    pub missing: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CascadeError {
+    // card `from` wins card `card`, which lies within the table but is not in it
+    MissingCard { from: u32, card: u32 },
+    // the number of instances of `card`, or the running total at `card`, no longer fits
+    CountOverflow { card: u32 },
+}
 
-        let inc_amount = num_cards[idx];
-        for item in num_cards.iter_mut().skip(idx + 1).take(matches) {
-            *item += inc_amount;
+impl fmt::Display for CascadeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CascadeError::MissingCard { from, card } => {
+                write!(
+                    f,
+                    "card {from} wins a copy of card {card}, which is missing"
+                )
+            }
+            CascadeError::CountOverflow { card } => {
+                write!(f, "instance count overflowed at card {card}")
+            }
         }
     }
+}
+
+impl Error for CascadeError {}
+
+// integer type used to count card instances, e.g. u64, u128 or num::BigUint
+pub trait Count: Clone + CheckedAdd + One + Zero {}
 
-    num_cards.iter().sum()
+impl<C: Clone + CheckedAdd + One + Zero> Count for C {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cascade<C = u32> {
+    instances: Vec<(u32, C)>,
+    copies: Vec<Copies<C>>,
+    overflow: Vec<Overflow>,
+    total: C,
+}
+
+impl<C: Count> Cascade<C> {
+    // final number of instances of each card, by card id
+    pub fn instances(&self) -> &[(u32, C)] {
+        &self.instances
+    }
+
+    pub fn copies(&self) -> &[Copies<C>] {
+        &self.copies
+    }
+
+    pub fn overflow(&self) -> &[Overflow] {
+        &self.overflow
+    }
+
+    pub fn total(&self) -> &C {
+        &self.total
+    }
+}
+
+// card n wins copies of cards n + 1 ..= n + matches, looked up by id; cards may be in any
+// order and the table need not start at card 1, but ids must be unique as `try_parse`
+// guarantees
+pub fn cascade(input: &[Card]) -> Result<Cascade, CascadeError> {
+    cascade_with(input)
+}
+
+// like `cascade`, counting with `C` and failing instead of wrapping when a count overflows
+pub fn cascade_with<C: Count>(input: &[Card]) -> Result<Cascade<C>, CascadeError> {
+    let mut order = (0..input.len()).collect::<Vec<_>>();
+    order.sort_by_key(|idx| input[*idx].id);
+
+    let by_id = order
+        .iter()
+        .enumerate()
+        .map(|(pos, idx)| (input[*idx].id, pos))
+        .collect::<HashMap<_, _>>();
+    let last_id = order.last().map(|idx| input[*idx].id).unwrap_or(0);
+
+    let mut num_cards = vec![C::one(); input.len()];
+    let mut copies = vec![];
+    let mut overflow = vec![];
+    let mut total = C::zero();
+
+    for (pos, card) in order.iter().map(|idx| &input[*idx]).enumerate() {
+        let inc_amount = num_cards[pos].clone();
+        total = total
+            .checked_add(&inc_amount)
+            .ok_or(CascadeError::CountOverflow { card: card.id })?;
+        // last card won that is still within the table
+        let reach = u32::try_from(card.matches)
+            .ok()
+            .and_then(|matches| card.id.checked_add(matches))
+            .map_or(last_id, |reach| reach.min(last_id));
+        for id in (card.id..reach).map(|id| id + 1) {
+            let Some(target) = by_id.get(&id) else {
+                return Err(CascadeError::MissingCard {
+                    from: card.id,
+                    card: id,
+                });
+            };
+            num_cards[*target] = num_cards[*target]
+                .checked_add(&inc_amount)
+                .ok_or(CascadeError::CountOverflow { card: id })?;
+            copies.push(Copies {
+                from: card.id,
+                to: id,
+                amount: inc_amount.clone(),
+            });
+        }
+
+        let missing = card.matches - (reach - card.id) as usize;
+        if missing > 0 {
+            overflow.push(Overflow {
+                card: card.id,
+                missing,
+            });
+        }
+    }
+
+    Ok(Cascade {
+        instances: order
+            .iter()
+            .map(|idx| input[*idx].id)
+            .zip(num_cards)
+            .collect(),
+        copies,
+        overflow,
+        total,
+    })
+}
+
+pub fn part2(input: Vec<Card>) -> Result<u64, CascadeError> {
+    cascade_with::<u64>(&input).map(|cascade| *cascade.total())
+}
+
+// a table where every card wins as many of the following cards as possible, up to
+// `matches`, so instance counts grow exponentially with the table length
+pub fn generate_table(cards: u32, matches: usize) -> String {
+    let winning = (1..=matches as u32).collect::<Vec<_>>();
+    (1..=cards)
+        .map(|id| {
+            let won = matches.min((cards - id) as usize);
+            let yours = winning[..won]
+                .iter()
+                .copied()
+                .chain((matches as u32 + 1..).take(matches - won))
+                .map(|n| n.to_string())
+                .collect::<Vec<_>>();
+            let winning = winning.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+            format!("Card {id}: {} | {}", winning.join(" "), yours.join(" "))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const SAMPLE: &str = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
+Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
+Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
+Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
+Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11";
+
     #[test]
     fn run04() {
         let input = include_str!("./input.txt");
@@ -80,4 +340,131 @@ mod tests {
         println!("{:?}", part1(&parsed));
         println!("{:?}", part2(parsed));
     }
+
+    #[test]
+    fn test_sample() {
+        let parsed = parse(SAMPLE);
+        let matches = parsed.iter().map(Card::matches).collect::<Vec<_>>();
+        assert_eq!(vec![4, 2, 2, 1, 0, 0], matches);
+        assert_eq!(Some(13), part1(&parsed));
+        assert_eq!(Ok(30), part2(parsed));
+    }
+
+    #[test]
+    fn test_cascade() {
+        let parsed = parse(SAMPLE);
+        let trace = cascade(&parsed).unwrap();
+        assert_eq!(
+            vec![(1, 1), (2, 2), (3, 4), (4, 8), (5, 14), (6, 1)],
+            trace.instances()
+        );
+        assert_eq!(
+            Copies {
+                from: 3,
+                to: 5,
+                amount: 4
+            },
+            trace.copies()[7]
+        );
+        assert!(trace.overflow().is_empty());
+
+        let parsed = parse("Card 1: 1 2 | 1 5\nCard 2: 1 2 3 | 1 2 3");
+        assert_eq!(
+            vec![Overflow {
+                card: 2,
+                missing: 3
+            }],
+            cascade(&parsed).unwrap().overflow()
+        );
+    }
+
+    #[test]
+    fn test_card_ids() {
+        let mut shuffled = SAMPLE.lines().rev().collect::<Vec<_>>();
+        shuffled.swap(1, 4);
+        let parsed = parse(&shuffled.join("\n"));
+        assert_eq!(
+            vec![1, 2, 3, 4, 5, 6],
+            parsed.iter().map(Card::id).collect::<Vec<_>>()
+        );
+        assert_eq!(Ok(30), part2(parsed));
+
+        let partial = parse(&SAMPLE.lines().skip(2).collect::<Vec<_>>().join("\n"));
+        let instances = cascade(&partial).unwrap().instances().to_vec();
+        assert_eq!(vec![(3, 1), (4, 2), (5, 4), (6, 1)], instances);
+
+        let gap = parse("Card 1: 1 2 | 1 2\nCard 3: 1 | 2\nCard 4: 1 | 2");
+        assert_eq!(
+            CascadeError::MissingCard { from: 1, card: 2 },
+            cascade(&gap).unwrap_err()
+        );
+        assert_eq!(
+            ParseError::DuplicateCard { card: 2 },
+            try_parse("Card 2: 1 | 2\nCard 1: 1 | 2\nCard 2: 3 | 4").unwrap_err()
+        );
+
+        let last = parse("Card 4294967294: 1 2 | 1 2\nCard 4294967295: 1 | 1");
+        let cascaded = cascade(&last).unwrap();
+        assert_eq!(
+            vec![(4294967294, 1), (4294967295, 2)],
+            cascaded.instances().to_vec()
+        );
+        assert_eq!(
+            vec![
+                Overflow {
+                    card: 4294967294,
+                    missing: 1
+                },
+                Overflow {
+                    card: 4294967295,
+                    missing: 1
+                }
+            ],
+            cascaded.overflow().to_vec()
+        );
+    }
+
+    #[test]
+    fn test_wide_counts() {
+        let parsed = parse(&generate_table(40, 40));
+        assert!(parsed
+            .iter()
+            .all(|card| card.matches() == 40 - card.id() as usize));
+        assert_eq!(Some((1u64 << 39) - 1), part1(&parsed));
+        assert_eq!(None, part1(&parse(&generate_table(70, 70))));
+        assert_eq!(
+            CascadeError::CountOverflow { card: 33 },
+            cascade(&parsed).unwrap_err()
+        );
+        assert_eq!(
+            (1u64 << 40) - 1,
+            *cascade_with::<u64>(&parsed).unwrap().total()
+        );
+        assert_eq!(Ok((1u64 << 40) - 1), part2(parsed));
+        assert_eq!(
+            Err(CascadeError::CountOverflow { card: 65 }),
+            part2(parse(&generate_table(70, 70)))
+        );
+
+        let parsed = parse(&generate_table(200, 200));
+        assert_eq!(
+            CascadeError::CountOverflow { card: 129 },
+            cascade_with::<u128>(&parsed).unwrap_err()
+        );
+        let big = cascade_with::<num::BigUint>(&parsed).unwrap();
+        assert_eq!(
+            (num::BigUint::one() << 200u32) - num::BigUint::one(),
+            *big.total()
+        );
+    }
+
+    #[test]
+    fn test_matches() {
+        let parsed = parse("Card 1: 3 200 1000 | 1000 7 200 4");
+        assert_eq!(2, parsed[0].matches());
+        assert_eq!(
+            ParseError::DuplicateNumber { card: 2, number: 5 },
+            try_parse("Card 1: 1 2 | 3 4\nCard 2: 5 6 | 5 7 5").unwrap_err()
+        );
+    }
 }
@@ -1,8 +1,13 @@
-use std::ops::Range;
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt,
+    ops::Range,
+};
 
 use nom::{
-    bytes::complete::{tag, take_until1},
-    character::complete::{i64 as parse_i64, line_ending},
+    bytes::complete::tag,
+    character::complete::{alpha1, i64 as parse_i64, line_ending},
     combinator::{all_consuming, map},
     multi::separated_list0,
     sequence::{preceded, tuple},
@@ -13,17 +18,35 @@ use nom::{
 pub struct Input {
     seeds: Vec<i64>,
     layers: Vec<Layer>,
+    chain: Vec<usize>, // indices of the layers leading from seed to location
 }
 
 #[derive(Debug)]
 pub struct Layer {
-    descriptions: Vec<(i64, i64, i64)>,
-    // based on input
-    mappings: Vec<(Range<i64>, i64)>, // filled in later
+    source: String,
+    destination: String,
+    mappings: PiecewiseMap,
+}
+
+// two source ranges of the same map that share values
+pub type Overlap = (Range<i64>, Range<i64>);
+
+// the almanac exactly as written, before any mappings are built or checked
+#[derive(Debug)]
+pub struct RawInput {
+    seeds: Vec<i64>,
+    layers: Vec<RawLayer>,
+}
+
+#[derive(Debug)]
+pub struct RawLayer {
+    source: String,
+    destination: String,
+    descriptions: Vec<(i64, i64, i64)>, // (dst_start, src_start, src_len)
 }
 
-fn parse_layer(i: &str) -> IResult<&str, Layer> {
-    let (i, _) = tuple((take_until1(" "), tag(" map:\n")))(i)?;
+fn parse_layer(i: &str) -> IResult<&str, RawLayer> {
+    let (i, (source, _, destination, _)) = tuple((alpha1, tag("-to-"), alpha1, tag(" map:\n")))(i)?;
 
     let (i, descriptions) = separated_list0(
         line_ending,
@@ -35,97 +58,909 @@ fn parse_layer(i: &str) -> IResult<&str, Layer> {
 
     Ok((
         i,
-        Layer {
+        RawLayer {
+            source: source.to_string(),
+            destination: destination.to_string(),
             descriptions,
-            mappings: Vec::new(),
         },
     ))
 }
 
-fn parse_input(i: &str) -> IResult<&str, Input> {
+fn parse_input(i: &str) -> IResult<&str, RawInput> {
     let (i, seeds) = preceded(tag("seeds: "), separated_list0(tag(" "), parse_i64))(i)?;
     let (i, layers) = separated_list0(tag("\n\n"), parse_layer)(i.trim())?;
 
-    Ok((i, Input { seeds, layers }))
+    Ok((i, RawInput { seeds, layers }))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    Syntax,
+    DuplicateMap {
+        source: String,
+        destination: String,
+    },
+    Cycle {
+        category: String,
+    },
+    // two lines of one map claim the same source values
+    Overlap {
+        source: String,
+        destination: String,
+        first: Range<i64>,
+        second: Range<i64>,
+    },
+    // the line's ranges or offset do not fit in an i64
+    Overflow {
+        source: String,
+        destination: String,
+        line: (i64, i64, i64),
+    },
+    // there must be exactly one chain of maps from seed to location
+    NoChain,
+    AmbiguousChain,
+    // a pair of part2's seed line, read as start and length, ends past i64::MAX
+    SeedOverflow {
+        start: i64,
+        len: i64,
+    },
 }
 
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Syntax => write!(f, "invalid syntax"),
+            ParseError::DuplicateMap {
+                source,
+                destination,
+            } => write!(f, "{source}-to-{destination} map appears twice"),
+            ParseError::Cycle { category } => write!(f, "maps loop back to {category}"),
+            ParseError::Overlap {
+                source,
+                destination,
+                first,
+                second,
+            } => write!(
+                f,
+                "{source}-to-{destination} map covers {first:?} and {second:?}, which overlap"
+            ),
+            ParseError::Overflow {
+                source,
+                destination,
+                line: (dst, src, len),
+            } => write!(
+                f,
+                "{source}-to-{destination} map line \"{dst} {src} {len}\" overflows"
+            ),
+            ParseError::NoChain => write!(f, "no maps lead from seed to location"),
+            ParseError::AmbiguousChain => {
+                write!(f, "more than one chain of maps leads from seed to location")
+            }
+            ParseError::SeedOverflow { start, len } => {
+                write!(f, "seed range of {len} starting at {start} overflows")
+            }
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    UnknownCategory(String),
+    NoPath { from: String, to: String },
+    Ambiguous { from: String, to: String },
+    // the maps shift some value further than one i64 offset can express
+    Overflow { from: String, to: String },
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::UnknownCategory(category) => write!(f, "unknown category {category}"),
+            QueryError::NoPath { from, to } => write!(f, "no maps lead from {from} to {to}"),
+            QueryError::Ambiguous { from, to } => {
+                write!(f, "more than one chain of maps leads from {from} to {to}")
+            }
+            QueryError::Overflow { from, to } => {
+                write!(f, "the maps from {from} to {to} overflow when composed")
+            }
+        }
+    }
+}
+
+impl Error for QueryError {}
+
 pub fn parse(input: &str) -> Input {
-    all_consuming(parse_input)(input.trim()).unwrap().1
+    try_parse(input).unwrap()
 }
 
-pub fn build_mappings(input: &mut Input) {
-    for layer in input.layers.iter_mut() {
-        for (dst_start, src_start, src_len) in &layer.descriptions {
-            layer
-                .mappings
-                .push((*src_start..(src_start + src_len), *dst_start - *src_start))
+pub fn try_parse(input: &str) -> Result<Input, ParseError> {
+    parse_raw(input)?.build()
+}
+
+pub fn parse_raw(input: &str) -> Result<RawInput, ParseError> {
+    Ok(all_consuming(parse_input)(input.trim())
+        .map_err(|_| ParseError::Syntax)?
+        .1)
+}
+
+impl RawLayer {
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn destination(&self) -> &str {
+        &self.destination
+    }
+
+    pub fn descriptions(&self) -> &[(i64, i64, i64)] {
+        &self.descriptions
+    }
+
+    fn pieces(&self) -> Result<Vec<(Range<i64>, i64)>, ParseError> {
+        self.descriptions
+            .iter()
+            .map(|&(dst_start, src_start, src_len)| {
+                let src_end = src_start.checked_add(src_len);
+                let dst_end = dst_start.checked_add(src_len);
+                let op = dst_start.checked_sub(src_start);
+                match (src_end, dst_end, op) {
+                    (Some(src_end), Some(_), Some(op)) => Ok((src_start..src_end, op)),
+                    _ => Err(ParseError::Overflow {
+                        source: self.source.clone(),
+                        destination: self.destination.clone(),
+                        line: (dst_start, src_start, src_len),
+                    }),
+                }
+            })
+            .collect()
+    }
+
+    // every pair of lines whose source ranges share values
+    pub fn overlaps(&self) -> Result<Vec<Overlap>, ParseError> {
+        let mut pieces = self.pieces()?;
+        pieces.retain(|(range, _)| !range.is_empty());
+        pieces.sort_by_key(|(range, _)| range.start);
+
+        let mut overlaps = vec![];
+        for (idx, (first, _)) in pieces.iter().enumerate() {
+            for (second, _) in &pieces[idx + 1..] {
+                if second.start >= first.end {
+                    break;
+                }
+                overlaps.push((first.clone(), second.clone()));
+            }
         }
-        layer.mappings.sort_by_key(|l| l.0.start)
+        Ok(overlaps)
+    }
+
+    fn build(self) -> Result<Layer, ParseError> {
+        if let Some((first, second)) = self.overlaps()?.into_iter().next() {
+            return Err(ParseError::Overlap {
+                source: self.source,
+                destination: self.destination,
+                first,
+                second,
+            });
+        }
+
+        Ok(Layer {
+            mappings: PiecewiseMap::new(self.pieces()?),
+            source: self.source,
+            destination: self.destination,
+        })
     }
 }
-pub fn part1(input: &Input) -> i64 {
-    input
-        .seeds
-        .iter()
-        .map(|seed| find_location_with_range(input, *seed).0)
-        .min()
-        .unwrap()
+
+impl RawInput {
+    pub fn seeds(&self) -> &[i64] {
+        &self.seeds
+    }
+
+    pub fn layers(&self) -> &[RawLayer] {
+        &self.layers
+    }
+
+    // map blocks may come in any order; every pair of categories may be mapped at most
+    // once, the maps must not form a cycle and exactly one chain of them must lead from
+    // seed to location. the seed ranges of part2 must fit in an i64
+    pub fn build(self) -> Result<Input, ParseError> {
+        validate(&self)?;
+        let mut input = Input {
+            seeds: self.seeds,
+            layers: self
+                .layers
+                .into_iter()
+                .map(RawLayer::build)
+                .collect::<Result<_, _>>()?,
+            chain: vec![],
+        };
+        input.chain = input.path("seed", "location").map_err(|err| match err {
+            QueryError::Ambiguous { .. } => ParseError::AmbiguousChain,
+            _ => ParseError::NoChain,
+        })?;
+        Ok(input)
+    }
 }
 
-fn find_location_with_range(input: &Input, seed_nr: i64) -> (i64, i64) {
-    let mut current = seed_nr;
-    let mut end_of_valid_range = i64::MAX;
+fn validate(input: &RawInput) -> Result<(), ParseError> {
+    for pair in input.seeds.chunks_exact(2) {
+        let (start, len) = (pair[0], pair[1]);
+        if start.checked_add(len).is_none() {
+            return Err(ParseError::SeedOverflow { start, len });
+        }
+    }
+
+    let mut seen = HashSet::new();
+    for layer in &input.layers {
+        if !seen.insert((&layer.source, &layer.destination)) {
+            return Err(ParseError::DuplicateMap {
+                source: layer.source.clone(),
+                destination: layer.destination.clone(),
+            });
+        }
+    }
+
+    // depth first search, marking categories on the current path
+    fn visit<'a>(
+        category: &'a str,
+        edges: &HashMap<&'a str, Vec<&'a str>>,
+        on_path: &mut HashSet<&'a str>,
+        done: &mut HashSet<&'a str>,
+    ) -> Result<(), ParseError> {
+        if done.contains(category) {
+            return Ok(());
+        }
+        if !on_path.insert(category) {
+            return Err(ParseError::Cycle {
+                category: category.to_string(),
+            });
+        }
+        for next in edges.get(category).into_iter().flatten() {
+            visit(next, edges, on_path, done)?;
+        }
+        on_path.remove(category);
+        done.insert(category);
+        Ok(())
+    }
+
+    let mut edges: HashMap<&str, Vec<&str>> = HashMap::new();
+    for layer in &input.layers {
+        edges
+            .entry(&layer.source)
+            .or_default()
+            .push(&layer.destination);
+    }
+    let (mut on_path, mut done) = (HashSet::new(), HashSet::new());
+    for layer in &input.layers {
+        visit(&layer.source, &edges, &mut on_path, &mut done)?;
+    }
 
-    'layers: for layer in &input.layers {
-        let mut last_range_end = 0i64;
-        for (range, op) in &layer.mappings {
-            if (last_range_end..range.start).contains(&current) {
-                // current stays unchanged
-                end_of_valid_range = end_of_valid_range.min(seed_nr + (range.start - current));
-                continue 'layers;
+    Ok(())
+}
+
+// sorted, disjoint, non-adjacent, non-empty ranges
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IntervalSet {
+    ranges: Vec<Range<i64>>,
+}
+
+impl From<Range<i64>> for IntervalSet {
+    fn from(range: Range<i64>) -> Self {
+        IntervalSet::new(std::iter::once(range))
+    }
+}
+
+impl IntervalSet {
+    pub fn new(ranges: impl IntoIterator<Item = Range<i64>>) -> Self {
+        let mut ranges = ranges
+            .into_iter()
+            .filter(|r| !r.is_empty())
+            .collect::<Vec<_>>();
+        ranges.sort_by_key(|r| r.start);
+
+        let mut merged: Vec<Range<i64>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        IntervalSet { ranges: merged }
+    }
+
+    pub fn ranges(&self) -> &[Range<i64>] {
+        &self.ranges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    // number of values in the set
+    pub fn size(&self) -> i64 {
+        self.ranges.iter().map(|r| r.end - r.start).sum()
+    }
+
+    pub fn min(&self) -> Option<i64> {
+        self.ranges.first().map(|r| r.start)
+    }
+
+    pub fn max(&self) -> Option<i64> {
+        self.ranges.last().map(|r| r.end - 1)
+    }
+
+    pub fn contains(&self, value: i64) -> bool {
+        let idx = self.ranges.partition_point(|r| r.end <= value);
+        self.ranges.get(idx).is_some_and(|r| r.contains(&value))
+    }
+
+    pub fn intersection(&self, other: &IntervalSet) -> IntervalSet {
+        let mut ranges = vec![];
+        let (mut a, mut b) = (0, 0);
+        while a < self.ranges.len() && b < other.ranges.len() {
+            let (l, r) = (&self.ranges[a], &other.ranges[b]);
+            ranges.push(l.start.max(r.start)..l.end.min(r.end));
+            if l.end < r.end {
+                a += 1;
+            } else {
+                b += 1;
+            }
+        }
+        IntervalSet::new(ranges)
+    }
+}
+
+// source ranges with the offset added to values inside them; anything not covered by a
+// piece maps to itself
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PiecewiseMap {
+    pieces: Vec<(Range<i64>, i64)>,
+}
+
+impl PiecewiseMap {
+    // the pieces must be disjoint, as `RawLayer::build` checks; everything else relies on it
+    pub(crate) fn new(pieces: impl IntoIterator<Item = (Range<i64>, i64)>) -> Self {
+        let mut pieces = pieces
+            .into_iter()
+            .filter(|(r, _)| !r.is_empty())
+            .collect::<Vec<_>>();
+        pieces.sort_by_key(|(r, _)| r.start);
+        PiecewiseMap { pieces }
+    }
+
+    pub fn pieces(&self) -> &[(Range<i64>, i64)] {
+        &self.pieces
+    }
+
+    pub fn map(&self, value: i64) -> i64 {
+        let idx = self.pieces.partition_point(|(r, _)| r.end <= value);
+        match self.pieces.get(idx) {
+            Some((range, op)) if range.contains(&value) => value + op,
+            _ => value,
+        }
+    }
+
+    // splits `range` at piece boundaries, pairing each part with its offset (0 in gaps)
+    fn segments(&self, range: Range<i64>) -> Vec<(Range<i64>, i64)> {
+        let mut out = vec![];
+        let mut cursor = range.start;
+        let first = self.pieces.partition_point(|(r, _)| r.end <= cursor);
+        for (mapped, op) in &self.pieces[first..] {
+            if mapped.start >= range.end {
+                break;
+            }
+            if cursor < mapped.start {
+                out.push((cursor..mapped.start, 0));
+                cursor = mapped.start;
             }
-            if range.contains(&current) {
-                end_of_valid_range = end_of_valid_range.min(seed_nr + (range.end - current));
-                current += op;
-                continue 'layers;
+            let end = range.end.min(mapped.end);
+            out.push((cursor..end, *op));
+            cursor = end;
+        }
+        if cursor < range.end {
+            out.push((cursor..range.end, 0));
+        }
+        out
+    }
+
+    // maps every value of `set` at once, splitting ranges at mapping boundaries
+    pub fn map_set(&self, set: &IntervalSet) -> IntervalSet {
+        IntervalSet::new(
+            set.ranges()
+                .iter()
+                .flat_map(|range| self.segments(range.clone()))
+                .map(|(range, op)| range.start + op..range.end + op),
+        )
+    }
+
+    // every value that `map` sends into `set`
+    pub fn preimage(&self, set: &IntervalSet) -> IntervalSet {
+        IntervalSet::new(
+            self.segments(i64::MIN..i64::MAX)
+                .into_iter()
+                .flat_map(|(range, op)| {
+                    set.intersection(&IntervalSet::from(range.start + op..range.end + op))
+                        .ranges
+                        .into_iter()
+                        .map(move |image| image.start - op..image.end - op)
+                }),
+        )
+    }
+
+    // a single mapping equivalent to applying `self` and then `next`; `None` if a combined
+    // offset does not fit in an i64
+    pub fn then(&self, next: &PiecewiseMap) -> Option<PiecewiseMap> {
+        let mut pieces: Vec<(Range<i64>, i64)> = vec![];
+        for (range, op) in self.segments(i64::MIN..i64::MAX) {
+            for (image, next_op) in next.segments(range.start + op..range.end + op) {
+                let piece = (image.start - op..image.end - op, op.checked_add(next_op)?);
+                match pieces.last_mut() {
+                    Some(last) if last.0.end == piece.0.start && last.1 == piece.1 => {
+                        last.0.end = piece.0.end
+                    }
+                    _ => pieces.push(piece),
+                }
             }
+        }
+        pieces.retain(|(_, op)| *op != 0);
+        Some(PiecewiseMap { pieces })
+    }
+}
 
-            last_range_end = range.end;
+impl fmt::Display for PiecewiseMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (range, op) in &self.pieces {
+            writeln!(
+                f,
+                "{}..{} -> {}..{} ({op:+})",
+                range.start,
+                range.end,
+                range.start + op,
+                range.end + op
+            )?;
         }
+        Ok(())
+    }
+}
 
-        // current and end_of_valid_range stay unchanged
+impl Layer {
+    pub fn source(&self) -> &str {
+        &self.source
     }
 
-    (current, end_of_valid_range)
+    pub fn destination(&self) -> &str {
+        &self.destination
+    }
+
+    pub fn mapping(&self) -> &PiecewiseMap {
+        &self.mappings
+    }
+
+    // the parts of `within` no line of the map covers, i.e. that map to themselves
+    pub fn gaps(&self, within: Range<i64>) -> IntervalSet {
+        let mut gaps = vec![];
+        let mut cursor = within.start;
+        for (range, _) in &self.mappings.pieces {
+            if range.start > cursor {
+                gaps.push(cursor..range.start.min(within.end));
+            }
+            cursor = cursor.max(range.end);
+        }
+        gaps.push(cursor..within.end);
+        IntervalSet::new(gaps)
+    }
+
+    pub fn map(&self, value: i64) -> i64 {
+        self.mappings.map(value)
+    }
+
+    pub fn map_set(&self, set: &IntervalSet) -> IntervalSet {
+        self.mappings.map_set(set)
+    }
+
+    pub fn preimage(&self, set: &IntervalSet) -> IntervalSet {
+        self.mappings.preimage(set)
+    }
 }
 
-pub fn part2(input: Input) -> i64 {
-    let mut min_position = i64::MAX;
-    for seed_instruction in input.seeds.chunks_exact(2) {
-        let mut seed = seed_instruction[0];
-        let length = seed_instruction[1];
-        let end = seed + length; // exclusive
+impl Input {
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    // category names in order of first appearance
+    pub fn categories(&self) -> Vec<&str> {
+        let mut categories: Vec<&str> = vec![];
+        for layer in &self.layers {
+            for category in [&layer.source, &layer.destination] {
+                if !categories.contains(&category.as_str()) {
+                    categories.push(category);
+                }
+            }
+        }
+        categories
+    }
+
+    // indices of the layers leading from category `from` to `to`, which must be unique
+    pub fn path(&self, from: &str, to: &str) -> Result<Vec<usize>, QueryError> {
+        for category in [from, to] {
+            let known = self
+                .layers
+                .iter()
+                .any(|layer| layer.source == category || layer.destination == category);
+            if !known {
+                return Err(QueryError::UnknownCategory(category.to_string()));
+            }
+        }
+
+        // the graph is acyclic, so a plain depth first search terminates
+        fn search(
+            input: &Input,
+            at: &str,
+            to: &str,
+            path: &mut Vec<usize>,
+            found: &mut Vec<Vec<usize>>,
+        ) {
+            if at == to {
+                found.push(path.clone());
+                return;
+            }
+            for (idx, layer) in input.layers.iter().enumerate() {
+                if layer.source == at && found.len() < 2 {
+                    path.push(idx);
+                    search(input, &layer.destination, to, path, found);
+                    path.pop();
+                }
+            }
+        }
 
-        while seed < end {
-            let (loc, end_valid) = find_location_with_range(&input, seed);
-            min_position = min_position.min(loc);
-            seed = end_valid;
+        let mut found = vec![];
+        search(self, from, to, &mut vec![], &mut found);
+        let (from, to) = (from.to_string(), to.to_string());
+        match found.len() {
+            0 => Err(QueryError::NoPath { from, to }),
+            1 => Ok(found.pop().unwrap()),
+            _ => Err(QueryError::Ambiguous { from, to }),
         }
     }
 
-    min_position
+    // the maps from `from` to `to` (e.g. soil to humidity) folded into one mapping
+    pub fn compose(&self, from: &str, to: &str) -> Result<PiecewiseMap, QueryError> {
+        self.path(from, to)?
+            .into_iter()
+            .try_fold(PiecewiseMap::default(), |acc, idx| {
+                acc.then(&self.layers[idx].mappings)
+            })
+            .ok_or_else(|| QueryError::Overflow {
+                from: from.to_string(),
+                to: to.to_string(),
+            })
+    }
+
+    pub fn convert(&self, from: &str, value: i64, to: &str) -> Result<i64, QueryError> {
+        Ok(self
+            .path(from, to)?
+            .into_iter()
+            .fold(value, |value, idx| self.layers[idx].map(value)))
+    }
+
+    fn seed_to_location(&self) -> impl DoubleEndedIterator<Item = &Layer> {
+        self.chain.iter().map(|idx| &self.layers[*idx])
+    }
+
+    // `None` if the composed offsets overflow, see `PiecewiseMap::then`
+    fn seed_to_location_map(&self) -> Option<PiecewiseMap> {
+        self.seed_to_location()
+            .try_fold(PiecewiseMap::default(), |acc, layer| {
+                acc.then(&layer.mappings)
+            })
+    }
+
+    pub fn location(&self, seed: i64) -> i64 {
+        self.seed_to_location()
+            .fold(seed, |value, layer| layer.map(value))
+    }
+
+    // part2's reading of the seed line: pairs of start and length
+    pub fn seed_ranges(&self) -> IntervalSet {
+        IntervalSet::new(
+            self.seeds
+                .chunks_exact(2)
+                .map(|pair| pair[0]..pair[0] + pair[1]),
+        )
+    }
+
+    // all seeds (not only the listed ones) that end up in `locations`
+    pub fn seeds_for_locations(&self, locations: &IntervalSet) -> IntervalSet {
+        self.seed_to_location()
+            .rev()
+            .fold(locations.clone(), |set, layer| layer.preimage(&set))
+    }
+
+    // interval sets are half-open and cannot hold i64::MAX, so that location finds no seeds
+    pub fn seeds_for_location(&self, location: i64) -> IntervalSet {
+        self.seeds_for_locations(&IntervalSet::from(location..location.saturating_add(1)))
+    }
+
+    pub fn location_ranges(&self) -> IntervalSet {
+        self.seed_to_location()
+            .fold(self.seed_ranges(), |set, layer| layer.map_set(&set))
+    }
+}
+
+// the parts are None only when the almanac lists no seeds
+pub fn part1(input: &Input) -> Option<i64> {
+    // walks the layers one by one when they cannot be composed
+    let seed_to_location = input.seed_to_location_map();
+    input
+        .seeds
+        .iter()
+        .map(|seed| match &seed_to_location {
+            Some(map) => map.map(*seed),
+            None => input.location(*seed),
+        })
+        .min()
+}
+
+pub fn part2(input: Input) -> Option<i64> {
+    input.location_ranges().min()
+}
+
+// part2 searched backwards: walk the composed mapping's pieces from the lowest location
+// upward and stop once no remaining piece can beat the best seed found; falls back to
+// mapping the seed ranges forwards when the layers cannot be composed
+pub fn part2_by_location(input: &Input) -> Option<i64> {
+    let Some(seed_to_location) = input.seed_to_location_map() else {
+        return input.location_ranges().min();
+    };
+    let seeds = input.seed_ranges();
+    let mut segments = seed_to_location.segments(i64::MIN..i64::MAX);
+    segments.sort_by_key(|(range, op)| range.start + op);
+
+    let mut best = None;
+    for (range, op) in segments {
+        if best.is_some_and(|best| range.start + op >= best) {
+            break;
+        }
+        let reached = seeds.intersection(&IntervalSet::from(range));
+        if let Some(seed) = reached.min() {
+            best = Some(best.map_or(seed + op, |best| best.min(seed + op)));
+        }
+    }
+    best
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const SAMPLE: &str = "seeds: 79 14 55 13
+
+seed-to-soil map:
+50 98 2
+52 50 48
+
+soil-to-fertilizer map:
+0 15 37
+37 52 2
+39 0 15
+
+fertilizer-to-water map:
+49 53 8
+0 11 42
+42 0 7
+57 7 4
+
+water-to-light map:
+88 18 7
+18 25 70
+
+light-to-temperature map:
+45 77 23
+81 45 19
+68 64 13
+
+temperature-to-humidity map:
+0 69 1
+1 0 69
+
+humidity-to-location map:
+60 56 37
+56 93 4";
+
     #[test]
     fn run05() {
         let input = include_str!("./input.txt");
-        let mut parsed = parse(input);
-        build_mappings(&mut parsed);
+        let parsed = parse(input);
         println!("{:?}", part1(&parsed));
+        println!("{:?}", part2_by_location(&parsed));
         println!("{:?}", part2(parsed));
     }
+
+    #[test]
+    fn test_sample() {
+        let raw = parse_raw(SAMPLE).unwrap();
+        assert_eq!((50, 98, 2), raw.layers()[0].descriptions()[0]);
+        let parsed = raw.build().unwrap();
+        assert_eq!(Some(35), part1(&parsed));
+        assert_eq!(Some(46), part2(parsed));
+
+        let seedless = parse("seeds: \n\nseed-to-location map:\n1 2 3");
+        assert_eq!(None, part1(&seedless));
+        assert_eq!(None, part2_by_location(&seedless));
+        assert_eq!(None, part2(seedless));
+    }
+
+    #[test]
+    fn test_interval_sets() {
+        let set = IntervalSet::new([10..20, 0..5, 5..7, 15..25, 30..30]);
+        assert_eq!(&[0..7, 10..25], set.ranges());
+        assert_eq!(22, set.size());
+        assert!(set.contains(24) && !set.contains(8));
+        assert_eq!(
+            &[3..7, 10..12],
+            set.intersection(&IntervalSet::new([3..12, 40..50]))
+                .ranges()
+        );
+
+        let parsed = parse(SAMPLE);
+        let soil = parsed.layers[0].map_set(&parsed.seed_ranges());
+        assert_eq!(&[57..70, 81..95], soil.ranges());
+        assert_eq!(
+            &[46..61, 82..85, 86..90, 94..99],
+            parsed.location_ranges().ranges()
+        );
+    }
+
+    #[test]
+    fn test_compose() {
+        let parsed = parse(SAMPLE);
+
+        let composed = parsed.compose("seed", "location").unwrap();
+        for seed in -5..120 {
+            assert_eq!(parsed.location(seed), composed.map(seed));
+        }
+        assert_eq!(
+            parsed.location_ranges(),
+            composed.map_set(&parsed.seed_ranges())
+        );
+
+        let soil_to_humidity = parsed.compose("soil", "humidity").unwrap();
+        assert_eq!(
+            parsed.layers[1].map(0),
+            parsed.compose("soil", "fertilizer").unwrap().map(0)
+        );
+        assert_eq!(22, soil_to_humidity.map(0));
+        assert_eq!(
+            Some("0..14 -> 22..36 (+22)"),
+            soil_to_humidity.to_string().lines().next()
+        );
+
+        let far = "seeds: -9000000000000000000 1\n\nseed-to-soil map:\n\
+                   0 -9000000000000000000 1\n\nsoil-to-location map:\n\
+                   9000000000000000000 0 1";
+        let parsed = parse(far);
+        assert_eq!(
+            Err(QueryError::Overflow {
+                from: "seed".to_string(),
+                to: "location".to_string()
+            }),
+            parsed.compose("seed", "location")
+        );
+        assert_eq!(9000000000000000000, parsed.location(-9000000000000000000));
+        assert_eq!(Some(1), part1(&parsed));
+        assert_eq!(Some(9000000000000000000), part2_by_location(&parsed));
+    }
+
+    #[test]
+    fn test_inverse() {
+        let parsed = parse(SAMPLE);
+
+        assert_eq!(Some(46), part2_by_location(&parsed));
+        assert!(parsed.seeds_for_location(46).contains(82));
+        assert!(parsed.seeds_for_location(35).contains(13));
+        assert!(parsed.seeds_for_location(i64::MAX).is_empty());
+
+        let locations = parsed.location_ranges();
+        let seeds = parsed.seed_ranges();
+        let preimage = parsed.seeds_for_locations(&locations);
+        assert_eq!(seeds, seeds.intersection(&preimage));
+        assert_eq!(
+            locations,
+            parsed
+                .compose("seed", "location")
+                .unwrap()
+                .map_set(&preimage)
+        );
+    }
+
+    #[test]
+    fn test_categories() {
+        let mut blocks = SAMPLE.split("\n\n").collect::<Vec<_>>();
+        blocks[1..].reverse();
+        let parsed = parse(&blocks.join("\n\n"));
+
+        assert_eq!("humidity", parsed.layers[0].source());
+        assert_eq!(Ok(16), parsed.convert("soil", 42, "temperature"));
+        assert_eq!(Some(35), part1(&parsed));
+        assert_eq!(
+            Err(QueryError::NoPath {
+                from: "water".to_string(),
+                to: "soil".to_string()
+            }),
+            parsed.convert("water", 1, "soil")
+        );
+        assert_eq!(
+            Err(QueryError::UnknownCategory("fuel".to_string())),
+            parsed.path("seed", "fuel")
+        );
+
+        let shortcut = format!("{SAMPLE}\n\nseed-to-light map:\n1 2 3");
+        assert!(matches!(
+            try_parse(&shortcut),
+            Err(ParseError::AmbiguousChain)
+        ));
+        let branches = format!(
+            "{SAMPLE}\n\nlocation-to-fuel map:\n1 2 3\n\nfuel-to-gas map:\n1 2 3\n\n\
+             location-to-gas map:\n1 2 3"
+        );
+        assert_eq!(
+            Err(QueryError::Ambiguous {
+                from: "location".to_string(),
+                to: "gas".to_string()
+            }),
+            parse(&branches).path("location", "gas")
+        );
+        assert!(matches!(
+            try_parse("seeds: 1 2\n\nseed-to-soil map:\n1 2 3"),
+            Err(ParseError::NoChain)
+        ));
+        let cycle = format!("{SAMPLE}\n\nlocation-to-soil map:\n1 2 3");
+        assert!(matches!(try_parse(&cycle), Err(ParseError::Cycle { .. })));
+        let twice = format!("{SAMPLE}\n\nseed-to-soil map:\n1 2 3");
+        assert!(matches!(
+            try_parse(&twice),
+            Err(ParseError::DuplicateMap { .. })
+        ));
+    }
+
+    #[test]
+    fn test_validation() {
+        let parsed = parse(SAMPLE);
+        let seed_to_soil = &parsed.layers()[0];
+        assert_eq!(IntervalSet::from(0..50), seed_to_soil.gaps(0..100));
+        assert_eq!(&[20..50, 100..120], seed_to_soil.gaps(20..120).ranges());
+        assert!(parsed.layers()[5].gaps(0..70).is_empty());
+
+        let overlapping = "seeds: 1\n\nseed-to-soil map:\n0 10 5\n20 0 5\n50 12 2\n90 13 8";
+        let raw = parse_raw(overlapping).unwrap();
+        assert_eq!(
+            Ok(vec![(10..15, 12..14), (10..15, 13..21), (12..14, 13..21)]),
+            raw.layers()[0].overlaps()
+        );
+        assert_eq!(
+            Err(ParseError::Overlap {
+                source: "seed".to_string(),
+                destination: "soil".to_string(),
+                first: 10..15,
+                second: 12..14
+            }),
+            raw.build().map(|_| ())
+        );
+
+        let overflowing = format!("seeds: 1\n\nseed-to-soil map:\n0 {} 2", i64::MAX - 1);
+        assert!(matches!(
+            try_parse(&overflowing),
+            Err(ParseError::Overflow { .. })
+        ));
+        assert_eq!(
+            Err(ParseError::SeedOverflow {
+                start: 9223372036854775800,
+                len: 100
+            }),
+            try_parse("seeds: 9223372036854775800 100\n\nseed-to-location map:\n1 2 3").map(|_| ())
+        );
+    }
 }
@@ -1,62 +1,257 @@
-pub fn part1(input: &str) -> u32 {
-    let mut lines = input.trim().lines();
-    let times = lines
-        .next()
-        .unwrap()
-        .split_ascii_whitespace()
-        .skip(1)
-        .map(|time| time.parse::<u32>().unwrap());
-    let distances = lines
-        .next()
-        .unwrap()
-        .split_ascii_whitespace()
-        .skip(1)
-        .map(|dist| dist.parse::<u32>().unwrap());
-
-    times
-        .zip(distances)
-        .map(|(time, best_dist)| {
-            let mut faster = 0u32;
-            for time_step in 0..time {
-                let travel_distance = time_step * (time - time_step);
-                if travel_distance > best_dist {
-                    faster += 1;
+use std::{error::Error, fmt, ops::RangeInclusive, str::FromStr};
+
+use num::{integer::Roots, BigUint, CheckedMul, Integer};
+
+pub mod physics;
+
+// unsigned integer types races can be solved in: u32 through u128, or BigUint
+pub trait RaceInt: Integer + Roots + CheckedMul + Clone {}
+
+impl<N: Integer + Roots + CheckedMul + Clone> RaceInt for N {}
+
+// whether holding for `hold` beats `record`, i.e. hold * (time - hold) > record,
+// rearranged so nothing can overflow
+fn wins<N: RaceInt>(time: &N, record: &N, hold: &N) -> bool {
+    if hold >= time {
+        return false;
+    }
+    let rest = time.clone() - hold.clone();
+    *hold > record.clone() / rest
+}
+
+// the hold times that beat the record, found via the quadratic formula with an integer
+// square root; falls back to a binary search when time^2 does not fit in `N`
+pub fn winning_holds<N: RaceInt>(time: &N, record: &N) -> Option<RangeInclusive<N>> {
+    let two = N::one() + N::one();
+    let four = two.clone() + two.clone();
+    let half = time.clone() / two.clone();
+    if !wins(time, record, &half) {
+        // the distance peaks at time / 2, so nothing wins
+        return None;
+    }
+
+    let mut lo = match time
+        .checked_mul(time)
+        .zip(record.checked_mul(&four))
+        .filter(|(square, four_record)| square > four_record)
+    {
+        Some((square, four_record)) => {
+            let root = (square - four_record).sqrt();
+            (time.clone() - root.min(time.clone())) / two
+        }
+        None => {
+            // smallest winning hold in [0, time / 2]
+            let (mut lo, mut hi) = (N::zero(), half);
+            while lo < hi {
+                let mid = lo.clone() + (hi.clone() - lo.clone()) / two.clone();
+                if wins(time, record, &mid) {
+                    hi = mid;
+                } else {
+                    lo = mid + N::one();
                 }
             }
-            faster
+            lo
+        }
+    };
+
+    // the root is exact up to rounding, so only a step in either direction is left
+    while !wins(time, record, &lo) {
+        lo = lo + N::one();
+    }
+    while lo > N::zero() && wins(time, record, &(lo.clone() - N::one())) {
+        lo = lo - N::one();
+    }
+
+    // distances are symmetric around time / 2
+    let hi = time.clone() - lo.clone();
+    Some(lo..=hi)
+}
+
+pub fn count_winning<N: RaceInt>(time: &N, record: &N) -> N {
+    match winning_holds(time, record) {
+        Some(holds) => holds.end().clone() - holds.start().clone() + N::one(),
+        None => N::zero(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Race<N = u64> {
+    pub time: N,
+    pub record: N,
+}
+
+// the sheet read both ways: one race per column, and one race with the columns'
+// digits concatenated
+// numbers are kept as validated digit strings so they can be read into any width
+#[derive(Debug)]
+pub struct Input {
+    races: Vec<Race<String>>,
+    kerned: Race<String>,
+}
+
+impl Input {
+    // `None` if some number does not fit in `N`
+    pub fn races<N: FromStr>(&self) -> Option<Vec<Race<N>>> {
+        self.races
+            .iter()
+            .map(|race| {
+                Some(Race {
+                    time: race.time.parse().ok()?,
+                    record: race.record.parse().ok()?,
+                })
+            })
+            .collect()
+    }
+
+    // `None` if the kerned numbers do not fit in `N`
+    pub fn kerned<N: FromStr>(&self) -> Option<Race<N>> {
+        Some(Race {
+            time: self.kerned.time.parse().ok()?,
+            record: self.kerned.record.parse().ok()?,
         })
-        .product()
-}
-
-pub fn part2(input: &str) -> u64 {
-    let mut lines = input.trim().lines();
-    let time = lines
-        .next()
-        .unwrap()
-        .split_once(':')
-        .unwrap()
-        .1
-        .replace(' ', "")
-        .parse::<u64>()
-        .unwrap();
-    let best_distance = lines
-        .next()
-        .unwrap()
-        .split_once(':')
-        .unwrap()
-        .1
-        .replace(' ', "")
-        .parse::<u64>()
-        .unwrap();
-
-    let mut faster = 0u64;
-    for time_step in 0..time {
-        let travel_distance = time_step * (time - time_step);
-        if travel_distance > best_distance {
-            faster += 1;
+    }
+}
+
+// lines are 1-based
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    MissingLine { label: &'static str },
+    MissingLabel { line: usize, label: &'static str },
+    InvalidNumber { line: usize, token: String },
+    ColumnMismatch { times: usize, records: usize },
+    NoRaces,
+    TrailingLine { line: usize },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::MissingLine { label } => write!(f, "missing \"{label}\" line"),
+            ParseError::MissingLabel { line, label } => {
+                write!(f, "line {line} should start with \"{label}\"")
+            }
+            ParseError::InvalidNumber { line, token } => {
+                write!(f, "line {line}: {token:?} is not a valid number")
+            }
+            ParseError::ColumnMismatch { times, records } => {
+                write!(f, "{times} times but {records} distance records")
+            }
+            ParseError::NoRaces => write!(f, "no races listed"),
+            ParseError::TrailingLine { line } => {
+                write!(f, "line {line}: unexpected line after the distances")
+            }
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+fn parse_line<'a>(
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+    label: &'static str,
+) -> Result<Vec<&'a str>, ParseError> {
+    let (idx, line) = lines.next().ok_or(ParseError::MissingLine { label })?;
+    let rest = line.strip_prefix(label).ok_or(ParseError::MissingLabel {
+        line: idx + 1,
+        label,
+    })?;
+    let tokens = rest.split_ascii_whitespace().collect::<Vec<_>>();
+    for token in &tokens {
+        if !token.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(ParseError::InvalidNumber {
+                line: idx + 1,
+                token: token.to_string(),
+            });
         }
     }
-    faster
+    Ok(tokens)
+}
+
+pub fn parse(input: &str) -> Input {
+    try_parse(input).unwrap()
+}
+
+pub fn try_parse(input: &str) -> Result<Input, ParseError> {
+    let mut lines = input.trim().lines().enumerate();
+    let times = parse_line(&mut lines, "Time:")?;
+    let records = parse_line(&mut lines, "Distance:")?;
+    if times.len() != records.len() {
+        return Err(ParseError::ColumnMismatch {
+            times: times.len(),
+            records: records.len(),
+        });
+    }
+    if times.is_empty() {
+        return Err(ParseError::NoRaces);
+    }
+    if let Some((idx, _)) = lines.next() {
+        return Err(ParseError::TrailingLine { line: idx + 1 });
+    }
+
+    let races = times
+        .iter()
+        .zip(&records)
+        .map(|(time, record)| Race {
+            time: time.to_string(),
+            record: record.to_string(),
+        })
+        .collect();
+    let kerned = Race {
+        time: times.concat(),
+        record: records.concat(),
+    };
+    Ok(Input { races, kerned })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RaceReport<N = u64> {
+    pub holds: Option<RangeInclusive<N>>,
+    pub ways: N,
+    // holding for time / 2 goes furthest (time - time / 2 ties with it)
+    pub best_hold: N,
+    // `None` if the distance does not fit in `N`
+    pub best_distance: Option<N>,
+    // how far past the record the best hold goes, `None` if it cannot be beaten
+    pub margin: Option<N>,
+}
+
+pub fn report<N: RaceInt>(race: &Race<N>) -> RaceReport<N> {
+    let best_hold = race.time.clone() / (N::one() + N::one());
+    let best_distance = best_hold.checked_mul(&(race.time.clone() - best_hold.clone()));
+    let margin = best_distance
+        .clone()
+        .filter(|distance| *distance > race.record)
+        .map(|distance| distance - race.record.clone());
+    RaceReport {
+        holds: winning_holds(&race.time, &race.record),
+        ways: count_winning(&race.time, &race.record),
+        best_hold,
+        best_distance,
+        margin,
+    }
+}
+
+// `None` if a number or the product does not fit in a u64
+pub fn part1(input: &Input) -> Option<u64> {
+    input
+        .races::<u64>()?
+        .iter()
+        .try_fold(1u64, |product, race| {
+            product.checked_mul(count_winning(&race.time, &race.record))
+        })
+}
+
+// `None` if the kerned numbers do not fit in a u64, see `part2_big`
+pub fn part2(input: &Input) -> Option<u64> {
+    let race = input.kerned::<u64>()?;
+    Some(count_winning(&race.time, &race.record))
+}
+
+// part2 for kerned races too long for any fixed width integer
+pub fn part2_big(input: &Input) -> BigUint {
+    // try_parse only accepts non-empty columns of digits, which always parse
+    let race = input.kerned::<BigUint>().unwrap();
+    count_winning(&race.time, &race.record)
 }
 
 #[cfg(test)]
@@ -66,7 +261,161 @@ mod tests {
     #[test]
     fn run06() {
         let input = include_str!("./input.txt");
-        println!("{:?}", part1(input));
-        println!("{:?}", part2(input));
+        let parsed = parse(input);
+        println!("{:?}", part1(&parsed));
+        println!("{:?}", part2(&parsed));
+        assert_eq!(BigUint::from(part2(&parsed).unwrap()), part2_big(&parsed));
+    }
+
+    #[test]
+    fn test_sample() {
+        let parsed = parse("Time:      7  15   30\nDistance:  9  40  200");
+        assert_eq!(Some(288), part1(&parsed));
+        assert_eq!(Some(71503), part2(&parsed));
+    }
+
+    #[test]
+    fn test_closed_form() {
+        for time in 0u32..60 {
+            for record in 0..time * time / 4 + 2 {
+                let brute = (0..=time).filter(|hold| hold * (time - hold) > record);
+                let expected = brute.clone().min().map(|lo| lo..=brute.max().unwrap());
+                assert_eq!(expected, winning_holds(&time, &record), "{time} {record}");
+            }
+        }
+
+        // time^2 overflows u128 here, so this goes through the binary search
+        let (time, record) = (u128::MAX - 6, u128::MAX / 5);
+        let big = count_winning(&BigUint::from(time), &BigUint::from(record));
+        assert_eq!(big, BigUint::from(count_winning(&time, &record)));
+
+        let time = "9".repeat(60).parse::<BigUint>().unwrap();
+        let record = "1".repeat(100).parse::<BigUint>().unwrap();
+        let holds = winning_holds(&time, &record).unwrap();
+        let distance = |hold: &BigUint| hold * (&time - hold);
+        assert!(distance(holds.start()) > record);
+        assert!(distance(&(holds.start() - 1u32)) <= record);
+        assert_eq!(
+            count_winning(&time, &record),
+            part2_big(&parse(&format!("Time: {time}\nDistance: {record}")))
+        );
+    }
+
+    #[test]
+    fn test_parse_and_report() {
+        let parsed = parse("Time:      7  15   30\nDistance:  9  40  200");
+        assert_eq!(
+            Race {
+                time: 15,
+                record: 40
+            },
+            parsed.races().unwrap()[1]
+        );
+        assert_eq!(
+            Some(Race {
+                time: 71530u32,
+                record: 940200
+            }),
+            parsed.kerned()
+        );
+        assert_eq!(None, parsed.kerned::<u16>());
+
+        assert_eq!(
+            RaceReport {
+                holds: Some(2..=5),
+                ways: 4,
+                best_hold: 3,
+                best_distance: Some(12),
+                margin: Some(3),
+            },
+            report(&parsed.races().unwrap()[0])
+        );
+        assert_eq!(None, report(&Race { time: 4, record: 4 }).margin);
+
+        assert_eq!(
+            Err(ParseError::ColumnMismatch {
+                times: 2,
+                records: 3
+            }),
+            try_parse("Time: 1 2\nDistance: 3 4 5").map(|_| ())
+        );
+        assert_eq!(
+            Err(ParseError::InvalidNumber {
+                line: 2,
+                token: "4x".to_string()
+            }),
+            try_parse("Time: 1 2\nDistance: 3 4x").map(|_| ())
+        );
+        assert_eq!(
+            Err(ParseError::MissingLine { label: "Distance:" }),
+            try_parse("Time: 1 2").map(|_| ())
+        );
+        assert_eq!(
+            Err(ParseError::NoRaces),
+            try_parse("Time:\nDistance:").map(|_| ())
+        );
+        assert_eq!(
+            Err(ParseError::TrailingLine { line: 3 }),
+            try_parse("Time: 1\nDistance: 0\nTime: 2").map(|_| ())
+        );
+
+        let long = parse("Time: 1\nDistance: 99999999999999999999999");
+        assert_eq!(None, part1(&long));
+        assert_eq!(None, part2(&long));
+        assert_eq!(BigUint::from(0u32), part2_big(&long));
+        let wide = parse(&format!("Time: {0} {0}\nDistance: 0 0", 1u64 << 33));
+        assert_eq!(None, part1(&wide));
+    }
+
+    #[test]
+    fn test_physics() {
+        use physics::*;
+
+        fn brute(model: &dyn Physics, race: &Race) -> Option<RangeInclusive<u64>> {
+            let wins = (0..=race.time)
+                .filter(|hold| model.distance(*hold, race.time) > race.record as u128);
+            wins.clone().min().map(|lo| lo..=wins.max().unwrap())
+        }
+
+        // only provides the distance, so the closed forms are checked against the search
+        struct Searched<P>(P);
+
+        impl<P: Physics> Physics for Searched<P> {
+            fn distance(&self, hold: u64, time: u64) -> u128 {
+                self.0.distance(hold, time)
+            }
+        }
+
+        let models: Vec<Box<dyn Physics>> = vec![
+            Box::new(Linear),
+            Box::new(Quadratic),
+            Box::new(Capped { max_speed: 4 }),
+            Box::new(Capped { max_speed: 40 }),
+            Box::new(Penalty { delay: 3 }),
+            Box::new(Searched(Linear)),
+            Box::new(Searched(Penalty { delay: 3 })),
+            Box::new(Searched(Capped { max_speed: 4 })),
+        ];
+        for time in 0..30 {
+            for record in (0..400).step_by(7) {
+                let race = Race { time, record };
+                for model in &models {
+                    assert_eq!(brute(model.as_ref(), &race), model.winning_holds(&race));
+                }
+            }
+        }
+
+        let parsed = parse(include_str!("./input.txt"));
+        let race = parsed.kerned::<u64>().unwrap();
+        assert_eq!(part2(&parsed), Some(Linear.count_winning(&race)));
+        assert!(Quadratic.count_winning(&race) > Linear.count_winning(&race));
+        let capped = Capped {
+            max_speed: race.time / 3,
+        };
+        assert!(capped.count_winning(&race) < Linear.count_winning(&race));
+        assert_eq!(
+            Searched(capped).winning_holds(&race),
+            capped.winning_holds(&race)
+        );
     }
 }
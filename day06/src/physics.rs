@@ -0,0 +1,117 @@
+use std::ops::RangeInclusive;
+
+use crate::{winning_holds, Race};
+
+// how far a boat gets in a race of `time` ms after holding the button for `hold` ms
+//
+// over 0..=time the distance must not decrease up to its peak and must strictly decrease
+// after it, which is what the default binary search relies on
+pub trait Physics {
+    fn distance(&self, hold: u64, time: u64) -> u128;
+
+    fn winning_holds(&self, race: &Race) -> Option<RangeInclusive<u64>> {
+        let record = race.record as u128;
+        let distance = |hold| self.distance(hold, race.time);
+
+        // first hold after which the distance drops, i.e. the furthest reaching one
+        let peak = partition_point(0, race.time, |hold| distance(hold + 1) >= distance(hold));
+        if distance(peak) <= record {
+            return None;
+        }
+        let lo = partition_point(0, peak, |hold| distance(hold) <= record);
+        let hi = partition_point(peak, race.time, |hold| distance(hold + 1) > record);
+        Some(lo..=hi)
+    }
+
+    fn count_winning(&self, race: &Race) -> u64 {
+        self.winning_holds(race)
+            .map_or(0, |holds| holds.end() - holds.start() + 1)
+    }
+}
+
+// first value in lo..=hi for which `pred` is false, or `hi` if there is none
+fn partition_point(mut lo: u64, mut hi: u64, pred: impl Fn(u64) -> bool) -> u64 {
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if pred(mid) {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    lo
+}
+
+// the puzzle's model: speed equals hold time
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Linear;
+
+impl Physics for Linear {
+    fn distance(&self, hold: u64, time: u64) -> u128 {
+        hold.min(time) as u128 * (time - hold.min(time)) as u128
+    }
+
+    fn winning_holds(&self, race: &Race) -> Option<RangeInclusive<u64>> {
+        winning_holds(&race.time, &race.record)
+    }
+}
+
+// speed grows with the square of the hold time
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Quadratic;
+
+impl Physics for Quadratic {
+    fn distance(&self, hold: u64, time: u64) -> u128 {
+        let hold = hold.min(time) as u128;
+        hold.saturating_mul(hold)
+            .saturating_mul(time as u128 - hold)
+    }
+}
+
+// speed equals hold time, but never exceeds `max_speed`
+#[derive(Debug, Copy, Clone)]
+pub struct Capped {
+    pub max_speed: u64,
+}
+
+impl Physics for Capped {
+    fn distance(&self, hold: u64, time: u64) -> u128 {
+        let hold = hold.min(time);
+        hold.min(self.max_speed) as u128 * (time - hold) as u128
+    }
+
+    // the linear model up to the cap; past it the distance max_speed * (time - hold) only
+    // falls, so the winning holds end once that drops to the record
+    fn winning_holds(&self, race: &Race) -> Option<RangeInclusive<u64>> {
+        let holds = winning_holds(&race.time, &race.record)?;
+        if *holds.start() > self.max_speed {
+            return None;
+        }
+        if *holds.end() <= self.max_speed {
+            return Some(holds);
+        }
+        // max_speed >= holds.start() > 0 here, as holding for 0 ms never wins
+        let end = race.time - race.record / self.max_speed - 1;
+        Some(*holds.start()..=end)
+    }
+}
+
+// the first `delay` ms of holding only charge the boat, without adding speed
+#[derive(Debug, Copy, Clone)]
+pub struct Penalty {
+    pub delay: u64,
+}
+
+impl Physics for Penalty {
+    fn distance(&self, hold: u64, time: u64) -> u128 {
+        let hold = hold.min(time);
+        hold.saturating_sub(self.delay) as u128 * (time - hold) as u128
+    }
+
+    // the linear model on a race shortened by `delay`, shifted back by `delay`
+    fn winning_holds(&self, race: &Race) -> Option<RangeInclusive<u64>> {
+        let time = race.time.checked_sub(self.delay)?;
+        let holds = winning_holds(&time, &race.record)?;
+        Some(holds.start() + self.delay..=holds.end() + self.delay)
+    }
+}
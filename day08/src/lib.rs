@@ -22,7 +22,7 @@ pub struct Input {
     mappings: HashMap<&'static str, (&'static str, &'static str)>,
 }
 
-fn parse_input(i: &'static str) -> IResult<&str, Input> {
+fn parse_input(i: &'static str) -> IResult<&'static str, Input> {
     let (i, dirs) = many1(alt((value(LR::L, tag("L")), value(LR::R, tag("R")))))(i)?;
     let (i, _) = tag("\n\n")(i)?;
     let (i, mappings) = separated_list0(